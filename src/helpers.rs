@@ -1,4 +1,5 @@
-use classicube_sys::{Camera, Vec3};
+use classicube_helpers::entities::ENTITY_SELF_ID;
+use classicube_sys::{Camera, Entities, Entity_GetEyeHeight, Vec2, Vec3};
 use ncollide3d::na::Vector3;
 use tracing::warn;
 
@@ -71,7 +72,8 @@ pub fn vec3_to_vector3(v: &Vec3) -> Vector3<f32> {
     Vector3::new(v.x, v.y, v.z)
 }
 
-pub fn get_self_position_and_yaw() -> Option<(Vec3, f32)> {
+/// orientation is yaw then pitch, in radians
+pub fn get_self_position_and_orientation() -> Option<(Vec3, Vec2)> {
     if unsafe { Camera.Active.is_null() } {
         warn!("Camera.Active is null!");
         return None;
@@ -79,7 +81,29 @@ pub fn get_self_position_and_yaw() -> Option<(Vec3, f32)> {
     let camera = unsafe { &*Camera.Active };
     let position = camera.GetPosition.map(|f| unsafe { f(0.0) })?;
     let orientation = camera.GetOrientation.map(|f| unsafe { f() })?;
-    Some((position, orientation.x))
+    Some((position, orientation))
+}
+
+/// Our own player entity's eye position and yaw/pitch in radians, which
+/// differ from the camera's in third-person or when spectating.
+pub fn get_self_entity_position_and_orientation() -> Option<(Vec3, Vec2)> {
+    let entity_ptr = unsafe { Entities.List[usize::from(ENTITY_SELF_ID)] };
+    if entity_ptr.is_null() {
+        return None;
+    }
+    let eye_height = unsafe { Entity_GetEyeHeight(entity_ptr) };
+    let entity = unsafe { &*entity_ptr };
+    Some((
+        Vec3::new(
+            entity.Position.x,
+            entity.Position.y + eye_height,
+            entity.Position.z,
+        ),
+        Vec2 {
+            x: entity.Yaw.to_radians(),
+            y: entity.Pitch.to_radians(),
+        },
+    ))
 }
 
 #[test]
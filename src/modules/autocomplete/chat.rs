@@ -1,43 +1,79 @@
 #[cfg(test)]
 mod tests;
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
 
-use chatsounds::{Chatsounds, normalize_sentence};
+use chatsounds::{ChannelVolumeSink, Chatsounds, normalize_sentence};
+use classicube_helpers::entities::ENTITY_SELF_ID;
 use classicube_sys::{
     InputButtons, InputButtons_CCKEY_BACKSPACE, InputButtons_CCKEY_DELETE, InputButtons_CCKEY_DOWN,
     InputButtons_CCKEY_END, InputButtons_CCKEY_ENTER, InputButtons_CCKEY_ESCAPE,
     InputButtons_CCKEY_HOME, InputButtons_CCKEY_KP_ENTER, InputButtons_CCKEY_LCTRL,
     InputButtons_CCKEY_LEFT, InputButtons_CCKEY_LSHIFT, InputButtons_CCKEY_RCTRL,
     InputButtons_CCKEY_RIGHT, InputButtons_CCKEY_RSHIFT, InputButtons_CCKEY_SLASH,
-    InputButtons_CCKEY_TAB, InputButtons_CCKEY_UP,
+    InputButtons_CCKEY_UP,
 };
 use tracing::error;
 
+use super::{
+    SearchMode,
+    history::{HISTORY_FILE_PATH, History},
+    modifiers::{
+        Modifier, ModifierContext, format_modifier_hints, format_variant_hint, matching_modifiers,
+        modifier_context,
+    },
+};
 use crate::{
     modules::{
         FutureShared, SyncShared, ThreadShared,
+        chatsounds::{
+            entity_emitter::{EntityEmitter, spawn_stop_silenced, stop_sink},
+            favorites::FAVORITES,
+            filter::SENTENCE_FILTER,
+            random::get_rng,
+            sentence_index::SENTENCE_INDEX,
+            usage::{USAGE, UsageScore, unix_now},
+        },
         event_handler::{simulate_char, simulate_key},
-        option::OptionModule,
+        option::{KEYBINDS, KeyAction, OptionModule},
     },
-    printer::status_forever,
+    printer::{status_forever, status_list},
 };
 
 const MAX_CHAT_INPUT: usize = 192;
 
+/// Volume of previews, relative to the chatsounds volume.
+const PREVIEW_VOLUME: f32 = 0.5;
+
+/// How many hints are listed at once.
+const HINT_LIST_LINES: usize = 3;
+/// Longer hints are cut off in the list.
+const HINT_LIST_WIDTH: usize = 48;
+
 pub struct Chat {
     open: bool,
     text: Vec<char>,
     cursor_pos: usize,
     dedupe_open_key: bool,
 
-    history: Vec<Vec<char>>,
+    history: History,
     history_pos: usize,
     history_restore: Option<Vec<char>>,
 
     search: Option<String>,
     hints: Option<Vec<(usize, String)>>,
+    hint_kind: HintKind,
     hint_pos: usize,
+    /// the part of `text` that Tab replaces
+    hint_segment: (usize, usize),
+    /// end of the last sentence filled in by Tab
+    completed_until: usize,
+    /// whether Tab has filled in a hint since they were made
+    tabbed: bool,
+    preview: Weak<ChannelVolumeSink>,
 
     held_keys: HashMap<InputButtons, bool>,
 
@@ -45,9 +81,21 @@ pub struct Chat {
     send_chat_key: InputButtons,
 
     chatsounds: FutureShared<Option<Chatsounds>>,
+    entity_emitters: ThreadShared<Vec<EntityEmitter>>,
     player_names: ThreadShared<Vec<String>>,
 }
 
+/// What `Chat::hints` are completing.
+enum HintKind {
+    Sentence,
+    /// one hint per modifier, in the same order
+    Modifier(Vec<&'static Modifier>),
+    Variant {
+        sentence: String,
+        count: usize,
+    },
+}
+
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 enum HintRender {
     OutOfBounds { hint_pos: usize, hints_len: usize },
@@ -79,7 +127,280 @@ fn starts_with_symbol(text: &str) -> bool {
         .is_some_and(|c| !c.is_alphanumeric())
 }
 
-fn format_hint(input: &str, hints: &[(usize, String)], hint_pos: usize) -> HintRender {
+/// Match quality of a substring match at the start of the hint.
+const PREFIX_QUALITY: f32 = 1.0;
+/// Match quality of a substring match at the start of a later word.
+const WORD_START_QUALITY: f32 = 0.9;
+/// Match quality of a substring match inside a word.
+const SUBSTRING_QUALITY: f32 = 0.8;
+/// Match quality of a subsequence filling the whole matched span.
+const SUBSEQUENCE_QUALITY: f32 = 0.6;
+/// Match quality of a match within typo distance with no typos.
+const TYPO_QUALITY: f32 = 0.5;
+/// One typo is allowed per this many typed characters.
+const CHARS_PER_TYPO: usize = 4;
+
+const QUALITY_WEIGHT: f32 = 0.6;
+const POPULARITY_WEIGHT: f32 = 0.1;
+const USAGE_WEIGHT: f32 = 0.2;
+const RECENCY_WEIGHT: f32 = 0.1;
+
+/// How well a substring match of `input` at `pos` in `candidate` matches,
+/// preferring the start of words and shorter candidates.
+fn substring_quality(input: &str, candidate: &str, pos: usize) -> f32 {
+    let base = if pos == 0 {
+        PREFIX_QUALITY
+    } else if candidate.get(..pos).is_some_and(|left| left.ends_with(' ')) {
+        WORD_START_QUALITY
+    } else {
+        SUBSTRING_QUALITY
+    };
+
+    #[allow(clippy::cast_precision_loss)]
+    let coverage = input.len() as f32 / candidate.len().max(1) as f32;
+    base - 0.1 * (1.0 - coverage.min(1.0))
+}
+
+/// Matches `input`'s characters in order with anything in between, returning
+/// where the match starts and its quality, which falls as the match spreads
+/// out.
+fn subsequence_match(input: &str, candidate: &str) -> Option<(usize, f32)> {
+    let mut chars = candidate.char_indices();
+    let mut first = None;
+    let mut end = 0;
+    for c in input.chars() {
+        let (i, d) = chars.find(|&(_, d)| d == c)?;
+        first.get_or_insert(i);
+        end = i + d.len_utf8();
+    }
+    let first = first?;
+
+    #[allow(clippy::cast_precision_loss)]
+    let density = input.len() as f32 / (end - first) as f32;
+    Some((first, SUBSEQUENCE_QUALITY * density))
+}
+
+/// The fewest single character insertions, deletions or substitutions that
+/// turn `input` into some substring of `candidate`.
+fn typo_distance(input: &[char], candidate: &[char]) -> usize {
+    // edits for input[..i] ending at the current candidate position
+    let mut column: Vec<usize> = (0..=input.len()).collect();
+    let mut best = input.len();
+
+    for &c in candidate {
+        let mut diagonal = column[0];
+        // a match can start anywhere in the candidate
+        column[0] = 0;
+        for i in 1..=input.len() {
+            let substitution = diagonal + usize::from(input[i - 1] != c);
+            diagonal = column[i];
+            column[i] = substitution.min(column[i] + 1).min(column[i - 1] + 1);
+        }
+        best = best.min(column[input.len()]);
+    }
+
+    best
+}
+
+/// Where and how well `input` matches `candidate`, or None if it doesn't.
+///
+/// Substring matches are always tried first; fuzzy mode falls back to
+/// subsequences and then to matches with a few typos. Both should already be
+/// lowercase.
+fn match_candidate(input: &str, candidate: &str, mode: SearchMode) -> Option<(usize, f32)> {
+    if let Some(pos) = candidate.find(input) {
+        return Some((pos, substring_quality(input, candidate, pos)));
+    }
+    if mode == SearchMode::Substring {
+        return None;
+    }
+
+    if let Some(found) = subsequence_match(input, candidate) {
+        return Some(found);
+    }
+
+    let input: Vec<char> = input.chars().collect();
+    let allowed = input.len() / CHARS_PER_TYPO;
+    if allowed == 0 {
+        return None;
+    }
+    let distance = typo_distance(&input, &candidate.chars().collect::<Vec<_>>());
+    if distance > allowed {
+        return None;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let quality = TYPO_QUALITY * (1.0 - distance as f32 / input.len() as f32);
+    Some((0, quality))
+}
+
+/// Matches `input` against every candidate, returning `(pos, candidate,
+/// quality)` for the ones that match.
+fn search_candidates<'a, I>(
+    candidates: I,
+    input: &str,
+    mode: SearchMode,
+) -> Vec<(usize, String, f32)>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let (pos, quality) = match_candidate(input, &candidate.to_ascii_lowercase(), mode)?;
+            Some((pos, candidate.to_string(), quality))
+        })
+        .collect()
+}
+
+/// Like `search_candidates`, for `(candidate, lowercased candidate)` pairs.
+fn search_lowercased_candidates<'a, I>(
+    candidates: I,
+    input: &str,
+    mode: SearchMode,
+) -> Vec<(usize, String, f32)>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    candidates
+        .into_iter()
+        .filter_map(|(candidate, lowercase)| {
+            let (pos, quality) = match_candidate(input, lowercase, mode)?;
+            Some((pos, candidate.to_string(), quality))
+        })
+        .collect()
+}
+
+/// Weighted combination of how well a hint matched and how much it's used.
+fn rank_score(quality: f32, usage: &UsageScore) -> f32 {
+    QUALITY_WEIGHT * quality
+        + POPULARITY_WEIGHT * usage.popularity
+        + USAGE_WEIGHT * usage.usage
+        + RECENCY_WEIGHT * usage.recency
+}
+
+/// Sorts `(pos, hint, quality)` matches best first, breaking ties by match
+/// position then length like a plain substring search.
+fn rank_hints<F>(matches: Vec<(usize, String, f32)>, usage: F) -> Vec<(usize, String)>
+where
+    F: Fn(&str) -> UsageScore,
+{
+    let mut ranked: Vec<(f32, usize, String)> = matches
+        .into_iter()
+        .map(|(pos, hint, quality)| (rank_score(quality, &usage(&hint)), pos, hint))
+        .collect();
+
+    ranked.sort_by(|(score1, pos1, hint1), (score2, pos2, hint2)| {
+        score2
+            .total_cmp(score1)
+            .then_with(|| pos1.cmp(pos2))
+            .then_with(|| hint1.len().cmp(&hint2.len()))
+    });
+
+    ranked
+        .into_iter()
+        .map(|(_score, pos, hint)| (pos, hint))
+        .collect()
+}
+
+/// Moves favorites to the front, keeping the order otherwise.
+fn favorites_first<F>(hints: &mut [(usize, String)], is_favorite: F)
+where
+    F: Fn(&str) -> bool,
+{
+    hints.sort_by_key(|(_pos, hint)| !is_favorite(hint));
+}
+
+/// Where the sentence being typed at `cursor` starts, so earlier sentences
+/// are kept when completing.
+///
+/// A sentence starts after the last one filled in by Tab (`completed_until`)
+/// or after a modifier (`:echo(..)`, `#2`, `%50`) followed by a space.
+fn segment_start(text: &[char], cursor: usize, completed_until: usize) -> usize {
+    let mut start = if completed_until <= cursor {
+        completed_until
+    } else {
+        0
+    };
+
+    let mut depth = 0usize;
+    let mut in_modifier = false;
+    for (i, &c) in text[..cursor].iter().enumerate() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && in_modifier {
+                    in_modifier = false;
+                    start = start.max(i + 1);
+                }
+            }
+            _ if depth > 0 => {}
+            ':' | '#' | '%' => in_modifier = true,
+            c if c.is_whitespace() && in_modifier => {
+                in_modifier = false;
+                start = start.max(i + 1);
+            }
+            _ => {}
+        }
+    }
+
+    while start < cursor && text[start].is_whitespace() {
+        start += 1;
+    }
+    start
+}
+
+/// `continued` marks a hint for a sentence after the start of the line.
+fn format_hint(
+    input: &str,
+    hints: &[(usize, String)],
+    hint_pos: usize,
+    continued: bool,
+) -> HintRender {
+    let render = format_segment_hint(input, hints, hint_pos);
+    if !continued {
+        return render;
+    }
+
+    match render {
+        HintRender::Full(s) | HintRender::Colored(s) => HintRender::Colored(format!("&7...&f{s}")),
+        out_of_bounds @ HintRender::OutOfBounds { .. } => out_of_bounds,
+    }
+}
+
+/// Lines listing the hints around `hint_pos`, top to bottom, with the one
+/// `format_hint` shows highlighted and followed by an `n/m` counter.
+///
+/// Empty when there's only one hint, since `format_hint` already shows it.
+fn format_hint_list(hints: &[(usize, String)], hint_pos: usize) -> Vec<String> {
+    if hints.len() < 2 || hint_pos >= hints.len() {
+        return Vec::new();
+    }
+
+    let lines = HINT_LIST_LINES.min(hints.len());
+    let first = hint_pos.saturating_sub(lines / 2).min(hints.len() - lines);
+
+    (first..first + lines)
+        .map(|i| {
+            let hint = &hints[i].1;
+            let hint = if hint.chars().count() > HINT_LIST_WIDTH {
+                let cut: String = hint.chars().take(HINT_LIST_WIDTH - 2).collect();
+                format!("{cut}..")
+            } else {
+                hint.clone()
+            };
+
+            if i == hint_pos {
+                format!("&f{hint} &e{}/{}", i + 1, hints.len())
+            } else {
+                format!("&7{hint}")
+            }
+        })
+        .collect()
+}
+
+fn format_segment_hint(input: &str, hints: &[(usize, String)], hint_pos: usize) -> HintRender {
     let input_len = input.len();
 
     let Some((pos, hint)) = hints.get(hint_pos) else {
@@ -90,6 +411,15 @@ fn format_hint(input: &str, hints: &[(usize, String)], hint_pos: usize) -> HintR
     };
     let pos = *pos;
 
+    if !hint
+        .get(pos..pos + input_len)
+        .is_some_and(|mid| mid.eq_ignore_ascii_case(input))
+    {
+        // a fuzzy match doesn't contain the input as typed, so there's nothing
+        // to highlight
+        return HintRender::Colored(format!("&7{hint}"));
+    }
+
     if pos == 0 && hint.len() == input_len {
         return HintRender::Full(hint.clone());
     }
@@ -123,7 +453,9 @@ impl Chat {
     pub fn new(
         option_module: &SyncShared<OptionModule>,
         chatsounds: FutureShared<Option<Chatsounds>>,
+        entity_emitters: ThreadShared<Vec<EntityEmitter>>,
         player_names: ThreadShared<Vec<String>>,
+        history: History,
     ) -> Self {
         #[allow(clippy::unnecessary_cast)]
         let open_chat_key = option_module.borrow_mut().open_chat_key.unwrap_or(0 as _);
@@ -135,27 +467,49 @@ impl Chat {
             open: false,
             cursor_pos: 0,
             dedupe_open_key: false,
-            history: Vec::new(),
+            history,
             history_pos: 0,
             history_restore: None,
             search: None,
             hints: None,
+            hint_kind: HintKind::Sentence,
             hint_pos: 0,
+            hint_segment: (0, 0),
+            completed_until: 0,
+            tabbed: false,
+            preview: Weak::new(),
             held_keys: HashMap::new(),
 
             open_chat_key,
             send_chat_key,
             chatsounds,
+            entity_emitters,
             player_names,
         }
     }
 
     async fn update_hints(&mut self) {
         self.hints = None;
+        self.hint_kind = HintKind::Sentence;
         self.hint_pos = 0;
+        self.tabbed = false;
+        self.stop_preview();
 
         let raw = self.get_text();
-        let input = normalize_sentence(&raw);
+
+        let start = segment_start(&self.text, self.cursor_pos, self.completed_until);
+        let segment: String = self.text[start..self.cursor_pos].iter().collect();
+        self.hint_segment = (start, self.cursor_pos);
+
+        if !starts_with_symbol(&raw) {
+            if let Some(context) = modifier_context(&segment) {
+                self.update_modifier_hints(context).await;
+                self.render_hints();
+                return;
+            }
+        }
+
+        let input = normalize_sentence(&segment);
 
         // Commands (`/...`) and mentions (`@...`) start with a symbol that is not
         // part of any chatsound or player name. Completing them would rewrite the
@@ -163,30 +517,65 @@ impl Chat {
         // ClassiCube's native Tab handling apply instead.
         if !starts_with_symbol(&raw) && input.len() >= 2 {
             let mut results: Vec<(usize, String)> = Vec::new();
+            let mode = OptionModule::autocomplete_mode();
 
             // Player usernames first (case-insensitive), then chatsounds.
             {
                 let names = self.player_names.lock().unwrap();
-                results.extend(search_player_names(&names, &input));
+                results.extend(match mode {
+                    SearchMode::Substring => search_player_names(&names, &input),
+                    SearchMode::Fuzzy => rank_hints(
+                        search_candidates(
+                            names
+                                .iter()
+                                .filter(|name| name.len() <= MAX_CHAT_INPUT)
+                                .map(String::as_str),
+                            &input,
+                            mode,
+                        ),
+                        |_| UsageScore::default(),
+                    ),
+                });
             }
 
             if let Some(chatsounds) = self.chatsounds.lock().await.as_mut() {
-                results.extend(
-                    chatsounds
+                let matches = match mode {
+                    SearchMode::Substring => chatsounds
                         .search(&input)
                         .iter()
-                        .filter_map(|(pos, sentence)| {
-                            if sentence.len() <= MAX_CHAT_INPUT {
-                                Some((*pos, (*sentence).clone()))
-                            } else {
-                                None
-                            }
-                        }),
-                );
+                        .map(|(pos, sentence)| {
+                            (
+                                *pos,
+                                (*sentence).clone(),
+                                substring_quality(&input, sentence, *pos),
+                            )
+                        })
+                        .collect(),
+                    SearchMode::Fuzzy => {
+                        search_lowercased_candidates(SENTENCE_INDEX.lock().iter(), &input, mode)
+                    }
+                };
+
+                let sentence_filter = SENTENCE_FILTER.lock();
+                let matches = matches
+                    .into_iter()
+                    .filter(|(_pos, sentence, _quality)| {
+                        sentence.len() <= MAX_CHAT_INPUT && sentence_filter.is_allowed(sentence)
+                    })
+                    .collect();
+
+                let usage = USAGE.lock();
+                let now = unix_now();
+                results.extend(rank_hints(matches, |sentence| usage.score(sentence, now)));
             } else {
                 error!("self.chatsounds is None");
             }
 
+            {
+                let favorites = FAVORITES.lock();
+                favorites_first(&mut results, |hint| favorites.contains(hint));
+            }
+
             if !results.is_empty() {
                 self.search = Some(input);
                 self.hints = Some(results);
@@ -196,14 +585,75 @@ impl Chat {
         self.render_hints();
     }
 
+    async fn update_modifier_hints(&mut self, context: ModifierContext<'_>) {
+        match context {
+            ModifierContext::Modifier { prefix, partial } => {
+                let modifiers = matching_modifiers(partial);
+                if modifiers.is_empty() {
+                    return;
+                }
+
+                self.hints = Some(
+                    modifiers
+                        .iter()
+                        .map(|modifier| (0, format!("{prefix}{}", modifier.completion())))
+                        .collect(),
+                );
+                self.hint_kind = HintKind::Modifier(modifiers);
+            }
+
+            ModifierContext::Variant {
+                prefix,
+                sentence,
+                partial,
+            } => {
+                let sentence = normalize_sentence(sentence);
+                let count = if let Some(chatsounds) = self.chatsounds.lock().await.as_mut() {
+                    chatsounds.get(&sentence).map_or(0, Vec::len)
+                } else {
+                    error!("self.chatsounds is None");
+                    0
+                };
+
+                let hints: Vec<(usize, String)> = (1..=count)
+                    .map(|n| n.to_string())
+                    .filter(|n| n.starts_with(partial))
+                    .map(|n| (0, format!("{prefix}#{n}")))
+                    .collect();
+                if hints.is_empty() {
+                    return;
+                }
+
+                self.hints = Some(hints);
+                self.hint_kind = HintKind::Variant { sentence, count };
+            }
+        }
+    }
+
     fn render_hints(&mut self) {
         let Some(hints) = &self.hints else {
             status_forever("");
+            status_list(&[]);
             return;
         };
 
+        match &self.hint_kind {
+            HintKind::Sentence => status_list(&format_hint_list(hints, self.hint_pos)),
+            HintKind::Modifier(modifiers) => {
+                status_forever(format_modifier_hints(modifiers, self.hint_pos));
+                status_list(&[]);
+                return;
+            }
+            HintKind::Variant { sentence, count } => {
+                status_forever(format_variant_hint(sentence, *count));
+                status_list(&[]);
+                return;
+            }
+        }
+
         let input = self.search.as_ref().unwrap();
-        match format_hint(input, hints, self.hint_pos) {
+        let continued = self.hint_segment.0 > 0;
+        match format_hint(input, hints, self.hint_pos, continued) {
             HintRender::OutOfBounds {
                 hint_pos,
                 hints_len,
@@ -216,20 +666,98 @@ impl Chat {
         self.text.iter().collect()
     }
 
-    pub fn set_text<T: Into<String>>(&mut self, text: T) {
-        let text = text.into();
+    /// Index of the hint Tab last filled in, or the highlighted one before any
+    /// Tab.
+    fn selected_pos(&self) -> Option<usize> {
+        let hints_len = self.hints.as_ref()?.len();
 
-        simulate_key(InputButtons_CCKEY_END);
-        for _ in 0..192 {
-            simulate_key(InputButtons_CCKEY_BACKSPACE);
+        Some(if self.tabbed {
+            self.hint_pos.checked_sub(1).unwrap_or(hints_len - 1)
+        } else {
+            self.hint_pos
+        })
+    }
+
+    fn selected_hint(&self) -> Option<&str> {
+        let HintKind::Sentence = self.hint_kind else {
+            return None;
+        };
+        let pos = self.selected_pos()?;
+
+        self.hints
+            .as_ref()?
+            .get(pos)
+            .map(|(_pos, hint)| hint.as_str())
+    }
+
+    /// Plays the selected hint for us only, without sending it.
+    async fn preview_selected(&mut self) {
+        self.stop_preview();
+
+        let Some(sentence) = self.selected_hint().map(ToString::to_string) else {
+            return;
+        };
+
+        if let Some(chatsounds) = self.chatsounds.lock().await.as_mut() {
+            if let Ok((sink, _played_chatsounds)) = chatsounds
+                .play_channel_volume(&sentence, get_rng(""), vec![PREVIEW_VOLUME, PREVIEW_VOLUME])
+                .await
+            {
+                self.preview = Arc::downgrade(&sink);
+                // tracked so that stopping and the max duration apply
+                self.entity_emitters
+                    .lock()
+                    .unwrap()
+                    .push(EntityEmitter::new_2d(
+                        ENTITY_SELF_ID,
+                        String::new(),
+                        &sink,
+                        PREVIEW_VOLUME,
+                    ));
+            }
+        } else {
+            error!("self.chatsounds is None");
+        }
+    }
+
+    fn stop_preview(&mut self) {
+        if self.preview.strong_count() == 0 {
+            return;
+        }
+
+        stop_sink(&mut self.entity_emitters.lock().unwrap(), &self.preview);
+        self.preview = Weak::new();
+        spawn_stop_silenced(self.chatsounds.clone(), self.entity_emitters.clone());
+    }
+
+    /// Replaces the hint segment, which ends at the cursor, with
+    /// `replacement`, leaving the rest of the line alone.
+    fn replace_segment(&mut self, replacement: &str) {
+        let (start, end) = self.hint_segment;
+        let replacement: Vec<char> = replacement.chars().collect();
+        if self.text.len() - (end - start) + replacement.len() > MAX_CHAT_INPUT {
+            return;
         }
 
-        for chr in text.chars() {
+        for _ in start..end {
+            simulate_key(InputButtons_CCKEY_BACKSPACE);
+        }
+        for &chr in &replacement {
             simulate_char(chr);
         }
 
-        self.text = text.chars().collect();
-        self.cursor_pos = self.text.len();
+        let end = start + replacement.len();
+        self.text.splice(start..self.hint_segment.1, replacement);
+        self.cursor_pos = end;
+        self.hint_segment = (start, end);
+        self.completed_until = end;
+    }
+
+    /// Editing inside a sentence filled in by Tab makes it a normal one again.
+    fn edited_at(&mut self, pos: usize) {
+        if pos < self.completed_until {
+            self.completed_until = 0;
+        }
     }
 
     fn handle_char_insert(&mut self, chr: char) {
@@ -242,6 +770,7 @@ impl Chat {
             return;
         }
 
+        self.edited_at(self.cursor_pos);
         self.text.insert(self.cursor_pos, chr);
         self.cursor_pos += 1;
     }
@@ -249,6 +778,17 @@ impl Chat {
     #[allow(clippy::cognitive_complexity)]
     #[allow(clippy::too_many_lines)]
     async fn handle_key(&mut self, key: InputButtons) {
+        let action = KEYBINDS
+            .lock()
+            .action(key, self.is_ctrl_held(), self.is_shift_held());
+        // the key's char never reaches us or the chat input, see
+        // `AutocompleteEventListener::swallow_action_press`
+        if let Some(action) = action
+            && self.handle_action(action).await
+        {
+            return;
+        }
+
         if key == InputButtons_CCKEY_LEFT {
             if self.is_ctrl_held() {
                 let mut found_non_space = false;
@@ -328,12 +868,14 @@ impl Chat {
                 self.text.remove(self.cursor_pos - 1);
                 self.cursor_pos -= 1;
             }
+            self.edited_at(self.cursor_pos);
 
             self.update_hints().await;
         } else if key == InputButtons_CCKEY_DELETE {
             if self.cursor_pos < self.text.len() && self.text.get(self.cursor_pos).is_some() {
                 self.text.remove(self.cursor_pos);
             }
+            self.edited_at(self.cursor_pos);
 
             self.update_hints().await;
         } else if key == InputButtons_CCKEY_HOME {
@@ -350,11 +892,13 @@ impl Chat {
                 self.history_restore = Some(self.text.clone());
             }
 
-            if self.history_pos < self.history.len() {
+            let entries = &self.history.entries;
+            if self.history_pos < entries.len() {
                 self.history_pos += 1;
-                self.text = self.history[self.history.len() - self.history_pos].clone();
+                self.text = entries[entries.len() - self.history_pos].clone();
                 self.cursor_pos = self.text.len();
             }
+            self.completed_until = 0;
 
             self.update_hints().await;
         } else if key == InputButtons_CCKEY_DOWN {
@@ -363,9 +907,10 @@ impl Chat {
                 return;
             }
 
+            let entries = &self.history.entries;
             if self.history_pos > 1 {
                 self.history_pos -= 1;
-                self.text = self.history[self.history.len() - self.history_pos].clone();
+                self.text = entries[entries.len() - self.history_pos].clone();
             } else if self.history_pos == 1 {
                 self.history_pos -= 1;
                 if let Some(history_restore) = &self.history_restore {
@@ -379,37 +924,93 @@ impl Chat {
                 }
             }
             self.cursor_pos = self.text.len();
+            self.completed_until = 0;
 
             self.update_hints().await;
-        } else if key == InputButtons_CCKEY_TAB {
-            if let Some(hints) = &self.hints {
-                let hints_len = hints.len();
-
-                if self.is_shift_held() {
-                    // go in reverse
+        }
+    }
 
-                    if self.hint_pos > 0 {
-                        self.hint_pos -= 1;
-                    } else {
-                        self.hint_pos = hints_len - 1;
-                    }
-                } else if self.hint_pos + 1 < hints_len {
-                    self.hint_pos += 1;
-                } else {
-                    self.hint_pos = 0;
-                }
+    /// returns whether the key was used up by `action`
+    async fn handle_action(&mut self, action: KeyAction) -> bool {
+        match action {
+            KeyAction::NextHint => self.cycle_hint(false).await,
+            KeyAction::PreviousHint => self.cycle_hint(true).await,
+            KeyAction::AcceptHint => self.accept_hint(),
+            KeyAction::Preview => {
+                self.preview_selected().await;
+                true
+            }
+            // done by the chatsounds listener, only with chat closed
+            KeyAction::StopAll | KeyAction::Mute => false,
+        }
+    }
 
-                // TODO if hint matches input then must tab, shift-tab to get the last item
+    /// returns false when there are no hints, so native Tab's display stands
+    async fn cycle_hint(&mut self, reverse: bool) -> bool {
+        if self.hints.is_some() && self.cursor_pos != self.hint_segment.1 {
+            // the cursor moved since the hints were made
+            self.update_hints().await;
+            return true;
+        }
 
-                let show_pos = self.hint_pos.checked_sub(1).unwrap_or(hints_len - 1);
+        let Some(hints) = &self.hints else {
+            return false;
+        };
+        let hints_len = hints.len();
 
-                let (_pos, sentence) = &hints[show_pos];
-                let sentence = sentence.clone();
-                self.set_text(sentence);
-                self.render_hints();
+        if reverse {
+            if self.hint_pos > 0 {
+                self.hint_pos -= 1;
+            } else {
+                self.hint_pos = hints_len - 1;
             }
-            // When hints is None, do nothing -- native Tab's display stands.
+        } else if self.hint_pos + 1 < hints_len {
+            self.hint_pos += 1;
+        } else {
+            self.hint_pos = 0;
+        }
+
+        // TODO if hint matches input then must tab, shift-tab to get the last item
+
+        let show_pos = self.hint_pos.checked_sub(1).unwrap_or(hints_len - 1);
+
+        let (_pos, sentence) = &hints[show_pos];
+        let sentence = sentence.clone();
+        self.replace_segment(&sentence);
+        self.tabbed = true;
+        self.render_hints();
+
+        if OptionModule::autocomplete_preview() {
+            self.preview_selected().await;
+        } else {
+            self.stop_preview();
+        }
+
+        true
+    }
+
+    /// Fills in the selected hint if Tab hasn't already, and moves on from it.
+    fn accept_hint(&mut self) -> bool {
+        if self.cursor_pos != self.hint_segment.1 {
+            return false;
+        }
+        let Some(pos) = self.selected_pos() else {
+            return false;
+        };
+
+        if !self.tabbed {
+            let (_pos, hint) = &self.hints.as_ref().unwrap()[pos];
+            let hint = hint.clone();
+            self.replace_segment(&hint);
         }
+
+        self.hints = None;
+        self.hint_pos = 0;
+        self.tabbed = false;
+        self.stop_preview();
+        self.render_hints();
+
+        true
     }
 
     pub async fn handle_key_down(&mut self, key: InputButtons, repeating: bool) {
@@ -422,6 +1023,7 @@ impl Chat {
                 self.history_restore = None;
                 self.hints = None;
                 self.hint_pos = 0;
+                self.completed_until = 0;
 
                 if key == InputButtons_CCKEY_SLASH {
                     self.handle_char_insert('/');
@@ -439,17 +1041,25 @@ impl Chat {
             let chat_send_success = key == self.send_chat_key || key == InputButtons_CCKEY_KP_ENTER;
 
             if chat_send_success || key == InputButtons_CCKEY_ESCAPE {
-                if chat_send_success {
-                    self.history.push(self.text.clone());
+                if chat_send_success
+                    && self
+                        .history
+                        .push(self.text.clone(), OptionModule::history_dedupe())
+                {
+                    if let Err(e) = self.history.save(HISTORY_FILE_PATH) {
+                        error!(?e, "saving history");
+                    }
                 }
 
                 self.open = false;
+                self.stop_preview();
                 self.text.clear();
                 self.cursor_pos = 0;
                 self.history_pos = 0;
                 self.history_restore = None;
                 self.hints = None;
                 self.hint_pos = 0;
+                self.completed_until = 0;
 
                 self.render_hints();
 
@@ -1,6 +1,11 @@
 use chatsounds::normalize_sentence;
 
-use super::{HintRender, format_hint, search_player_names, starts_with_symbol};
+use super::{
+    HintRender, favorites_first, format_hint, format_hint_list, match_candidate, rank_hints,
+    search_candidates, search_lowercased_candidates, search_player_names, segment_start,
+    starts_with_symbol, typo_distance,
+};
+use crate::modules::{autocomplete::SearchMode, chatsounds::usage::UsageScore};
 
 fn hint(pos: usize, sentence: &str) -> (usize, String) {
     (pos, sentence.to_string())
@@ -36,7 +41,7 @@ fn normalized_input_with_slash_renders() {
     let input = normalize_sentence("/foo");
     assert_eq!(input, "foo");
     assert_eq!(
-        format_hint(&input, &hints, 0),
+        format_hint(&input, &hints, 0, false),
         HintRender::Colored("foo&7 bar".to_string()),
     );
 }
@@ -46,7 +51,7 @@ fn full_match_short_circuits() {
     let hints = vec![hint(0, "foo bar")];
     let input = normalize_sentence("foo bar");
     assert_eq!(
-        format_hint(&input, &hints, 0),
+        format_hint(&input, &hints, 0, false),
         HintRender::Full("foo bar".to_string()),
     );
 }
@@ -56,7 +61,7 @@ fn mid_sentence_match_includes_left_context() {
     let hints = vec![hint(4, "foo bar")];
     let input = normalize_sentence("bar");
     assert_eq!(
-        format_hint(&input, &hints, 0),
+        format_hint(&input, &hints, 0, false),
         HintRender::Colored("&7foo &fbar".to_string()),
     );
 }
@@ -65,7 +70,7 @@ fn mid_sentence_match_includes_left_context() {
 fn out_of_bounds_index_reports_oob() {
     let hints = vec![hint(0, "foo bar")];
     assert_eq!(
-        format_hint("foo", &hints, 5),
+        format_hint("foo", &hints, 5, false),
         HintRender::OutOfBounds {
             hint_pos: 5,
             hints_len: 1,
@@ -107,7 +112,7 @@ fn inputs_with_normalize_artifacts_render_cleanly() {
     let hints = vec![hint(0, "weve got")];
     for raw in ["we've got", "we've  got", "we've-got", "we've_got"] {
         let input = normalize_sentence(raw);
-        let result = format_hint(&input, &hints, 0);
+        let result = format_hint(&input, &hints, 0, false);
         assert!(
             matches!(result, HintRender::Full(_) | HintRender::Colored(_)),
             "raw={raw:?} normalized={input:?} produced {result:?}",
@@ -122,7 +127,7 @@ fn player_case_insensitive_renders_real_case() {
     let hints = vec![hint(0, "SpiralP")];
     let input = normalize_sentence("spir"); // "spir"
     assert_eq!(
-        format_hint(&input, &hints, 0),
+        format_hint(&input, &hints, 0, false),
         HintRender::Colored("Spir&7alP".to_string()),
     );
 }
@@ -133,7 +138,7 @@ fn player_full_match_shows_real_case() {
     let hints = vec![hint(0, "SpiralP")];
     let input = "SpiralP".to_string();
     assert_eq!(
-        format_hint(&input, &hints, 0),
+        format_hint(&input, &hints, 0, false),
         HintRender::Full("SpiralP".to_string()),
     );
 }
@@ -184,3 +189,267 @@ fn player_no_match_returns_empty() {
     let found = search_player_names(&names(&["SpiralP"]), "zzz");
     assert!(found.is_empty());
 }
+
+// --- fuzzy matching ---
+
+fn chars(s: &str) -> Vec<char> {
+    s.chars().collect()
+}
+
+#[test]
+fn typo_distance_finds_best_substring() {
+    assert_eq!(typo_distance(&chars("hello"), &chars("oh hello there")), 0);
+    assert_eq!(typo_distance(&chars("helo"), &chars("oh hello there")), 1);
+    assert_eq!(typo_distance(&chars("hwllo"), &chars("hello")), 1);
+    assert_eq!(typo_distance(&chars("abc"), &chars("xyz")), 3);
+}
+
+#[test]
+fn substring_mode_only_matches_substrings() {
+    assert!(match_candidate("ello", "hello", SearchMode::Substring).is_some());
+    assert!(match_candidate("hlo", "hello", SearchMode::Substring).is_none());
+    assert!(match_candidate("hlo", "hello", SearchMode::Fuzzy).is_some());
+}
+
+#[test]
+fn fuzzy_matches_subsequences_and_typos() {
+    // subsequence
+    let (pos, _) = match_candidate("wgot", "weve got", SearchMode::Fuzzy).unwrap();
+    assert_eq!(pos, 0);
+
+    // one typo per 4 typed characters
+    assert!(match_candidate("hwllo", "hello there", SearchMode::Fuzzy).is_some());
+    assert!(match_candidate("hwl", "hello there", SearchMode::Fuzzy).is_none());
+    assert!(match_candidate("hxxlo there", "hello there", SearchMode::Fuzzy).is_some());
+    assert!(match_candidate("zzzzzzzz", "hello there", SearchMode::Fuzzy).is_none());
+}
+
+#[test]
+fn match_quality_prefers_prefix_then_word_start() {
+    let quality = |input, candidate| {
+        match_candidate(input, candidate, SearchMode::Fuzzy)
+            .unwrap()
+            .1
+    };
+
+    assert!(quality("got", "got it") > quality("got", "weve got"));
+    assert!(quality("got", "weve got") > quality("got", "forgot"));
+    assert!(quality("got", "forgot") > quality("gt", "forgot"));
+    assert!(quality("gt", "forgot") > quality("gxot", "weve got it"));
+    // shorter is closer to what was typed
+    assert!(quality("got", "got") > quality("got", "got it"));
+}
+
+#[test]
+fn search_candidates_keeps_real_case() {
+    let found = search_candidates(["SpiralP", "zoe"], "spir", SearchMode::Fuzzy);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, 0);
+    assert_eq!(found[0].1, "SpiralP");
+}
+
+#[test]
+fn search_lowercased_candidates_matches_the_lowercased_form() {
+    let found = search_lowercased_candidates(
+        [("Hello There", "hello there"), ("bye", "bye")],
+        "hlo",
+        SearchMode::Fuzzy,
+    );
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].1, "Hello There");
+
+    assert!(
+        search_lowercased_candidates(
+            [("Hello There", "hello there")],
+            "hlo",
+            SearchMode::Substring
+        )
+        .is_empty()
+    );
+}
+
+// --- rank_hints ---
+
+#[test]
+fn rank_without_usage_sorts_by_quality_then_pos_then_length() {
+    let ranked = rank_hints(
+        search_candidates(
+            ["forgot", "weve got", "got it", "got"],
+            "got",
+            SearchMode::Fuzzy,
+        ),
+        |_| UsageScore::default(),
+    );
+    assert_eq!(
+        ranked,
+        vec![
+            hint(0, "got"),
+            hint(0, "got it"),
+            hint(5, "weve got"),
+            hint(3, "forgot"),
+        ],
+    );
+}
+
+#[test]
+fn rank_prefers_used_and_recent_sentences() {
+    let matches = search_candidates(["got it", "got milk"], "got", SearchMode::Fuzzy);
+
+    let ranked = rank_hints(matches.clone(), |sentence| {
+        if sentence == "got milk" {
+            UsageScore {
+                popularity: 0.0,
+                usage: 0.8,
+                recency: 0.9,
+            }
+        } else {
+            UsageScore::default()
+        }
+    });
+    assert_eq!(ranked[0], hint(0, "got milk"));
+
+    // popularity alone is a weaker signal than a better match
+    let ranked = rank_hints(
+        search_candidates(["got it", "forgot"], "got", SearchMode::Fuzzy),
+        |sentence| UsageScore {
+            popularity: if sentence == "forgot" { 0.5 } else { 0.0 },
+            ..UsageScore::default()
+        },
+    );
+    assert_eq!(ranked[0], hint(0, "got it"));
+}
+
+#[test]
+fn fuzzy_hint_renders_gray() {
+    let hints = vec![hint(0, "weve got")];
+    assert_eq!(
+        format_hint("wgot", &hints, 0, false),
+        HintRender::Colored("&7weve got".to_string()),
+    );
+
+    // a typo at the end of the hint doesn't slice out of bounds
+    let hints = vec![hint(4, "hello")];
+    assert_eq!(
+        format_hint("hellp", &hints, 0, false),
+        HintRender::Colored("&7hello".to_string()),
+    );
+}
+
+// --- segment_start ---
+
+#[test]
+fn segment_is_whole_line_without_boundaries() {
+    let text = chars("oh hello there");
+    assert_eq!(segment_start(&text, text.len(), 0), 0);
+}
+
+#[test]
+fn segment_starts_after_completed_sentence() {
+    let text = chars("oh hello there wor");
+    assert_eq!(segment_start(&text, text.len(), 14), 15);
+
+    // moving back before the completed sentence ignores it
+    assert_eq!(segment_start(&text, 5, 14), 0);
+}
+
+#[test]
+fn segment_starts_after_modifiers() {
+    let text = chars("hello:echo(0.1, 0.5) wor");
+    assert_eq!(segment_start(&text, text.len(), 0), 21);
+
+    let text = chars("hello#2 wor");
+    assert_eq!(segment_start(&text, text.len(), 0), 8);
+
+    let text = chars("hello%50  wor");
+    assert_eq!(segment_start(&text, text.len(), 0), 10);
+
+    // still typing the modifier or its arguments
+    let text = chars("hello:ec");
+    assert_eq!(segment_start(&text, text.len(), 0), 0);
+    let text = chars("hello:echo(0.1, 0");
+    assert_eq!(segment_start(&text, text.len(), 0), 0);
+}
+
+#[test]
+fn continued_hint_is_marked() {
+    let hints = vec![hint(0, "foo bar")];
+    assert_eq!(
+        format_hint("foo", &hints, 0, true),
+        HintRender::Colored("&7...&ffoo&7 bar".to_string()),
+    );
+    assert_eq!(
+        format_hint("foo bar", &hints, 0, true),
+        HintRender::Colored("&7...&ffoo bar".to_string()),
+    );
+    assert!(matches!(
+        format_hint("foo", &hints, 3, true),
+        HintRender::OutOfBounds { .. }
+    ));
+}
+
+// --- format_hint_list ---
+
+#[test]
+fn hint_list_empty_for_single_hint() {
+    assert!(format_hint_list(&[hint(0, "foo")], 0).is_empty());
+    assert!(format_hint_list(&[], 0).is_empty());
+}
+
+#[test]
+fn hint_list_highlights_selection_with_counter() {
+    let hints = vec![hint(0, "foo"), hint(0, "foo bar")];
+    assert_eq!(
+        format_hint_list(&hints, 1),
+        vec!["&7foo".to_string(), "&ffoo bar &e2/2".to_string()],
+    );
+}
+
+#[test]
+fn hint_list_window_follows_selection() {
+    let hints: Vec<_> = (0..10).map(|i| hint(0, &format!("foo {i}"))).collect();
+
+    assert_eq!(
+        format_hint_list(&hints, 0),
+        vec![
+            "&ffoo 0 &e1/10".to_string(),
+            "&7foo 1".to_string(),
+            "&7foo 2".to_string(),
+        ],
+    );
+    assert_eq!(
+        format_hint_list(&hints, 5),
+        vec![
+            "&7foo 4".to_string(),
+            "&ffoo 5 &e6/10".to_string(),
+            "&7foo 6".to_string(),
+        ],
+    );
+    assert_eq!(
+        format_hint_list(&hints, 9),
+        vec![
+            "&7foo 7".to_string(),
+            "&7foo 8".to_string(),
+            "&ffoo 9 &e10/10".to_string(),
+        ],
+    );
+
+    assert!(format_hint_list(&hints, 10).is_empty());
+}
+
+#[test]
+fn hint_list_cuts_long_hints() {
+    let long = "a".repeat(100);
+    let hints = vec![hint(0, &long), hint(0, "foo")];
+    let lines = format_hint_list(&hints, 1);
+    assert_eq!(lines[0], format!("&7{}..", "a".repeat(46)));
+}
+
+#[test]
+fn favorites_rank_first() {
+    let mut hints = vec![hint(0, "a"), hint(0, "b"), hint(0, "c"), hint(0, "d")];
+    favorites_first(&mut hints, |hint| hint == "c" || hint == "b");
+    assert_eq!(
+        hints,
+        vec![hint(0, "b"), hint(0, "c"), hint(0, "a"), hint(0, "d")]
+    );
+}
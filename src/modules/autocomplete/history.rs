@@ -0,0 +1,114 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+
+pub const HISTORY_FILE_PATH: &str = "plugins/chatsounds/history.txt";
+
+/// Older entries are dropped past this many.
+const MAX_HISTORY: usize = 200;
+
+/// Commands whose arguments shouldn't be written to disk.
+const PRIVATE_COMMANDS: &[&str] = &["/pass", "/login", "/setpass"];
+
+/// Sent chat input, oldest first, one entry per line in the file.
+#[derive(Default)]
+pub struct History {
+    pub entries: Vec<Vec<char>>,
+}
+
+impl History {
+    /// A missing file is an empty history.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut text = String::new();
+        for entry in &self.entries {
+            if is_private(entry) {
+                continue;
+            }
+            text.extend(entry);
+            text.push('\n');
+        }
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut history = Self::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            history.push(line.chars().collect(), false);
+        }
+        history
+    }
+
+    /// With `dedupe`, an earlier copy of `entry` is moved to the end instead
+    /// of being repeated.
+    ///
+    /// returns false if the entry was empty
+    pub fn push(&mut self, entry: Vec<char>, dedupe: bool) -> bool {
+        if entry.iter().all(|c| c.is_whitespace()) {
+            return false;
+        }
+
+        if dedupe {
+            self.entries.retain(|other| *other != entry);
+        }
+        self.entries.push(entry);
+
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.drain(..self.entries.len() - MAX_HISTORY);
+        }
+
+        true
+    }
+}
+
+fn is_private(entry: &[char]) -> bool {
+    let text: String = entry.iter().collect::<String>().to_lowercase();
+    let command = text.split_whitespace().next().unwrap_or_default();
+    PRIVATE_COMMANDS.contains(&command)
+}
+
+#[test]
+fn test_history() {
+    let entry = |s: &str| s.chars().collect::<Vec<_>>();
+
+    let mut history = History::default();
+    assert!(history.push(entry("hello"), true));
+    assert!(history.push(entry("world"), true));
+    assert!(history.push(entry("hello"), true));
+    assert_eq!(history.entries, vec![entry("world"), entry("hello")]);
+
+    assert!(history.push(entry("world"), false));
+    assert_eq!(history.entries.len(), 3);
+
+    assert!(!history.push(entry(""), true));
+    assert!(!history.push(entry("  "), true));
+
+    for i in 0..MAX_HISTORY {
+        history.push(entry(&format!("{i}")), true);
+    }
+    assert_eq!(history.entries.len(), MAX_HISTORY);
+    assert_eq!(history.entries[0], entry("0"));
+
+    let history = History::parse("a\n\nb\n");
+    assert_eq!(history.entries, vec![entry("a"), entry("b")]);
+
+    assert!(is_private(&entry("/pass hunter2")));
+    assert!(is_private(&entry("/Login hunter2")));
+    assert!(!is_private(&entry("/passage")));
+    assert!(!is_private(&entry("pass me the salt")));
+}
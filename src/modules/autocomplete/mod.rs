@@ -1,22 +1,73 @@
 mod chat;
+mod history;
+mod modifiers;
 
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use anyhow::{Error, Result, bail};
 use chatsounds::Chatsounds;
 use classicube_helpers::tab_list::{TabList, remove_color};
+use classicube_sys::{
+    Gui, InputButtons, InputButtons_CCKEY_LCTRL, InputButtons_CCKEY_LSHIFT,
+    InputButtons_CCKEY_RCTRL, InputButtons_CCKEY_RSHIFT,
+};
 use futures::{
     channel::mpsc::{UnboundedSender, unbounded},
     prelude::*,
 };
 
-use self::chat::Chat;
-use crate::modules::{
-    EventHandlerModule, FutureShared, FuturesModule, Module, OptionModule, SyncShared,
-    ThreadShared,
-    event_handler::{IncomingEvent, IncomingEventListener},
+use self::{
+    chat::Chat,
+    history::{HISTORY_FILE_PATH, History},
 };
+use crate::{
+    modules::{
+        EventHandlerModule, FutureShared, FuturesModule, Module, OptionModule, SyncShared,
+        ThreadShared,
+        chatsounds::entity_emitter::EntityEmitter,
+        event_handler::{IncomingEvent, IncomingEventListener, swallow_next_press},
+        option::{KEYBINDS, KeyAction},
+    },
+    printer::print,
+};
+
+/// How typed text is matched against chatsounds and player names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// the typed text must appear as is
+    Substring,
+    /// also matches subsequences and text with a few typos
+    Fuzzy,
+}
+
+impl FromStr for SearchMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "substring" => Ok(Self::Substring),
+            "fuzzy" => Ok(Self::Fuzzy),
+            _ => bail!("expected substring or fuzzy"),
+        }
+    }
+}
+
+impl Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Substring => write!(f, "substring"),
+            Self::Fuzzy => write!(f, "fuzzy"),
+        }
+    }
+}
 
 pub struct AutocompleteModule {
     option_module: SyncShared<OptionModule>,
     chatsounds: FutureShared<Option<Chatsounds>>,
+    entity_emitters: ThreadShared<Vec<EntityEmitter>>,
     event_handler_module: SyncShared<EventHandlerModule>,
     tab_list: SyncShared<TabList>,
 }
@@ -25,12 +76,14 @@ impl AutocompleteModule {
     pub fn new(
         option_module: SyncShared<OptionModule>,
         chatsounds: FutureShared<Option<Chatsounds>>,
+        entity_emitters: ThreadShared<Vec<EntityEmitter>>,
         event_handler_module: SyncShared<EventHandlerModule>,
         tab_list: SyncShared<TabList>,
     ) -> Self {
         Self {
             option_module,
             chatsounds,
+            entity_emitters,
             event_handler_module,
             tab_list,
         }
@@ -39,10 +92,17 @@ impl AutocompleteModule {
 
 impl Module for AutocompleteModule {
     fn load(&mut self) {
+        let history = History::load(HISTORY_FILE_PATH).unwrap_or_else(|e| {
+            print(format!("{}{}", classicube_helpers::color::RED, e));
+            History::default()
+        });
+
         let autocomplete_event_listener = AutocompleteEventListener::new(
             &self.option_module,
             self.chatsounds.clone(),
+            self.entity_emitters.clone(),
             self.tab_list.clone(),
+            history,
         );
 
         self.event_handler_module
@@ -57,19 +117,31 @@ pub struct AutocompleteEventListener {
     sender: UnboundedSender<IncomingEvent>,
     tab_list: SyncShared<TabList>,
     player_names: ThreadShared<Vec<String>>,
+    /// left and right, tracked here too since the chat runs on another
+    /// thread and a press has to be swallowed right away
+    ctrl_held: [bool; 2],
+    shift_held: [bool; 2],
 }
 
 impl AutocompleteEventListener {
     pub fn new(
         option_module: &SyncShared<OptionModule>,
         chatsounds: FutureShared<Option<Chatsounds>>,
+        entity_emitters: ThreadShared<Vec<EntityEmitter>>,
         tab_list: SyncShared<TabList>,
+        history: History,
     ) -> Self {
         let player_names: ThreadShared<Vec<String>> = ThreadShared::default();
 
         let (sender, mut receiver) = unbounded();
 
-        let mut chat = Chat::new(option_module, chatsounds, player_names.clone());
+        let mut chat = Chat::new(
+            option_module,
+            chatsounds,
+            entity_emitters,
+            player_names.clone(),
+            history,
+        );
 
         FuturesModule::spawn_future(async move {
             while let Some(event) = receiver.next().await {
@@ -99,6 +171,40 @@ impl AutocompleteEventListener {
             sender,
             tab_list,
             player_names,
+            ctrl_held: [false; 2],
+            shift_held: [false; 2],
+        }
+    }
+
+    fn handle_held_keys(&mut self, key: InputButtons, down: bool) {
+        match key {
+            InputButtons_CCKEY_LCTRL => self.ctrl_held[0] = down,
+            InputButtons_CCKEY_RCTRL => self.ctrl_held[1] = down,
+            InputButtons_CCKEY_LSHIFT => self.shift_held[0] = down,
+            InputButtons_CCKEY_RSHIFT => self.shift_held[1] = down,
+            _ => {}
+        }
+    }
+
+    /// Keys bound to hint actions shouldn't type their char into chat.
+    fn swallow_action_press(&self, key: InputButtons) {
+        // chat isn't open, so nothing would be typed
+        if !OptionModule::autocomplete() || unsafe { Gui.InputGrab.is_null() } {
+            return;
+        }
+
+        let ctrl = self.ctrl_held.contains(&true);
+        let shift = self.shift_held.contains(&true);
+        if matches!(
+            KEYBINDS.lock().action(key, ctrl, shift),
+            Some(
+                KeyAction::NextHint
+                    | KeyAction::PreviousHint
+                    | KeyAction::AcceptHint
+                    | KeyAction::Preview
+            )
+        ) {
+            swallow_next_press();
         }
     }
 
@@ -121,12 +227,22 @@ impl IncomingEventListener for AutocompleteEventListener {
         match event {
             // InputDown always fires before its paired InputPress, so one
             // refresh here covers both the char-typed and key-action paths.
-            IncomingEvent::InputDown(..) => {
+            IncomingEvent::InputDown(key, _repeating) => {
+                self.handle_held_keys(*key, true);
+                self.swallow_action_press(*key);
+
                 self.refresh_player_names();
                 FuturesModule::block_future(self.sender.send(event.clone())).unwrap();
             }
 
-            IncomingEvent::InputPress(_) | IncomingEvent::InputUp(..) => {
+            IncomingEvent::InputUp(key, _repeating) => {
+                self.handle_held_keys(*key, false);
+
+                // send and process in the same order
+                FuturesModule::block_future(self.sender.send(event.clone())).unwrap();
+            }
+
+            IncomingEvent::InputPress(_) => {
                 // TODO somehow block here on tab key_down
 
                 // send and process in the same order
@@ -0,0 +1,200 @@
+/// Characters that start modifier syntax after a sentence.
+const MODIFIER_CHARS: [char; 3] = [':', '#', '%'];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Modifier {
+    pub name: &'static str,
+    pub args: &'static [&'static str],
+}
+
+impl Modifier {
+    /// e.g. `:echo(delay, decay)`
+    pub fn signature(&self) -> String {
+        if self.args.is_empty() {
+            format!(":{}", self.name)
+        } else {
+            format!(":{}({})", self.name, self.args.join(", "))
+        }
+    }
+
+    /// What Tab fills in, leaving the cursor inside the parentheses.
+    pub fn completion(&self) -> String {
+        if self.args.is_empty() {
+            format!(":{}", self.name)
+        } else {
+            format!(":{}(", self.name)
+        }
+    }
+}
+
+/// The `:modifier(args)` modifiers chatsounds understands.
+pub const MODIFIERS: &[Modifier] = &[
+    Modifier {
+        name: "echo",
+        args: &["delay", "decay"],
+    },
+    Modifier {
+        name: "lfopitch",
+        args: &["time", "amount"],
+    },
+    Modifier {
+        name: "lfovolume",
+        args: &["time", "amount"],
+    },
+    Modifier {
+        name: "pitch",
+        args: &["rate"],
+    },
+    Modifier {
+        name: "realm",
+        args: &["name"],
+    },
+    Modifier {
+        name: "rep",
+        args: &["count"],
+    },
+    Modifier {
+        name: "volume",
+        args: &["level"],
+    },
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModifierContext<'a> {
+    /// typing a `:modifier` name
+    Modifier {
+        /// everything before the `:`
+        prefix: &'a str,
+        /// what's typed after the `:`
+        partial: &'a str,
+    },
+    /// typing a `#n` variant number
+    Variant {
+        /// everything before the `#`
+        prefix: &'a str,
+        sentence: &'a str,
+        /// what's typed after the `#`
+        partial: &'a str,
+    },
+}
+
+/// Whether `text` ends in a modifier or variant being typed after a sentence.
+pub fn modifier_context(text: &str) -> Option<ModifierContext<'_>> {
+    let symbol_pos = text.rfind([':', '#'])?;
+    let prefix = &text[..symbol_pos];
+    let partial = &text[symbol_pos + 1..];
+
+    let sentence = prefix
+        .find(MODIFIER_CHARS)
+        .map_or(prefix, |pos| &prefix[..pos])
+        .trim();
+    if sentence.is_empty() {
+        return None;
+    }
+
+    if text[symbol_pos..].starts_with(':') {
+        if !partial.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        Some(ModifierContext::Modifier { prefix, partial })
+    } else {
+        if !partial.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(ModifierContext::Variant {
+            prefix,
+            sentence,
+            partial,
+        })
+    }
+}
+
+pub fn matching_modifiers(partial: &str) -> Vec<&'static Modifier> {
+    let partial = partial.to_ascii_lowercase();
+    MODIFIERS
+        .iter()
+        .filter(|modifier| modifier.name.starts_with(&partial))
+        .collect()
+}
+
+/// Lists the modifiers with their arguments, starting from `selected` so it
+/// stays visible when the list is too long for the status line.
+pub fn format_modifier_hints(modifiers: &[&Modifier], selected: usize) -> String {
+    let len = modifiers.len();
+    (0..len)
+        .map(|i| {
+            let i = (selected + i) % len;
+            let color = if i == selected { "&f" } else { "&7" };
+            format!("{color}{}", modifiers[i].signature())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn format_variant_hint(sentence: &str, count: usize) -> String {
+    if count == 1 {
+        format!("&7{sentence} has &f1 &7variant, &f#1")
+    } else {
+        format!("&7{sentence} has &f{count} &7variants, &f#1&7-&f#{count}")
+    }
+}
+
+#[test]
+fn test_modifier_context() {
+    assert_eq!(
+        modifier_context("hello:ec"),
+        Some(ModifierContext::Modifier {
+            prefix: "hello",
+            partial: "ec",
+        })
+    );
+    assert_eq!(
+        modifier_context("hello:echo(0.1, 0.5):"),
+        Some(ModifierContext::Modifier {
+            prefix: "hello:echo(0.1, 0.5)",
+            partial: "",
+        })
+    );
+    assert_eq!(
+        modifier_context("oh hello%50#1"),
+        Some(ModifierContext::Variant {
+            prefix: "oh hello%50",
+            sentence: "oh hello",
+            partial: "1",
+        })
+    );
+
+    // typing arguments
+    assert_eq!(modifier_context("hello:echo(0.1"), None);
+    // no sentence
+    assert_eq!(modifier_context(":ec"), None);
+    assert_eq!(modifier_context("  #2"), None);
+    assert_eq!(modifier_context("hello"), None);
+    assert_eq!(modifier_context("hello#x"), None);
+}
+
+#[test]
+fn test_format_modifier_hints() {
+    assert_eq!(matching_modifiers("lfo").len(), 2);
+    assert_eq!(matching_modifiers("ECHO")[0].completion(), ":echo(");
+    assert!(matching_modifiers("zzz").is_empty());
+
+    let modifiers = matching_modifiers("lfo");
+    assert_eq!(
+        format_modifier_hints(&modifiers, 0),
+        "&f:lfopitch(time, amount) &7:lfovolume(time, amount)"
+    );
+    assert_eq!(
+        format_modifier_hints(&modifiers, 1),
+        "&f:lfovolume(time, amount) &7:lfopitch(time, amount)"
+    );
+
+    assert_eq!(
+        format_variant_hint("hello", 1),
+        "&7hello has &f1 &7variant, &f#1"
+    );
+    assert_eq!(
+        format_variant_hint("hello", 12),
+        "&7hello has &f12 &7variants, &f#1&7-&f#12"
+    );
+}
@@ -1,82 +1,217 @@
-use std::sync::{Arc, Weak};
+use std::{
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
 
-use chatsounds::ChannelVolumeSink;
+use chatsounds::{ChannelVolumeSink, Chatsounds};
 use classicube_helpers::entities::Entities;
 use classicube_sys::Vec3;
-use kira::{Frame, Panning};
-use ncollide3d::na::Vector3;
 
-use crate::{
-    helpers::{get_self_position_and_yaw, vec3_to_vector3},
-    modules::SyncShared,
+use super::{
+    occlusion::Occlusion,
+    spatializer::{Listener, SpatialSettings, spatialize},
 };
+use crate::modules::{FutureShared, FuturesModule, SyncShared, ThreadShared};
+
+/// How long a sound over the max duration takes to fade out.
+const FADE_OUT_DURATION: Duration = Duration::from_secs(1);
+
+/// State shared by every emitter's update on a tick.
+pub struct EmitterTick {
+    pub now: Instant,
+    /// None if the camera isn't available
+    pub listener: Option<Listener>,
+    pub max_duration: Option<Duration>,
+    pub spatial: SpatialSettings,
+    pub occlusion: bool,
+    /// whether this tick should re-cast occlusion rays
+    pub check_occlusion: bool,
+}
 
 pub struct EntityEmitter {
     entity_id: u8,
+    /// who played the sound, for stopping one player's sounds
+    real_name: String,
     sink: Weak<ChannelVolumeSink>,
     static_pos: Option<Vec3>,
+    /// false for 2D sounds (our own), which are only tracked for the max
+    /// duration and rate limits
+    positional: bool,
+    /// per-player volume override
+    volume: f32,
+    started: Instant,
+    /// last ray cast result, re-checked every `OCCLUSION_INTERVAL`
+    occlusion: Option<Occlusion>,
 }
 
 impl EntityEmitter {
-    pub fn new(entity_id: u8, sink: &Arc<ChannelVolumeSink>, static_pos: Option<Vec3>) -> Self {
+    pub fn new(
+        entity_id: u8,
+        real_name: String,
+        sink: &Arc<ChannelVolumeSink>,
+        static_pos: Option<Vec3>,
+        volume: f32,
+    ) -> Self {
         Self {
             entity_id,
+            real_name,
             sink: Arc::downgrade(sink),
             static_pos,
+            positional: true,
+            volume,
+            started: Instant::now(),
+            occlusion: None,
         }
     }
 
-    /// returns None to remove the emitter
-    pub fn update(&mut self, entities: &mut SyncShared<Entities>) -> Option<()> {
-        let emitter_pos = self.static_pos.or_else(|| {
-            let entity = entities.borrow_mut().get(self.entity_id)?;
-            let entity = entity.upgrade()?;
-            Some(entity.get_position())
-        })?;
+    pub fn new_2d(
+        entity_id: u8,
+        real_name: String,
+        sink: &Arc<ChannelVolumeSink>,
+        volume: f32,
+    ) -> Self {
+        Self {
+            positional: false,
+            ..Self::new(entity_id, real_name, sink, None, volume)
+        }
+    }
 
-        let (self_pos, self_rot_yaw) = get_self_position_and_yaw()?;
-        let channel_volumes =
-            EntityEmitter::coords_to_sink_channel_volumes(emitter_pos, self_pos, self_rot_yaw);
+    pub fn entity_id(&self) -> u8 {
+        self.entity_id
+    }
 
-        self.sink.upgrade()?.set_channel_volumes(channel_volumes);
+    pub fn real_name(&self) -> &str {
+        &self.real_name
+    }
 
-        Some(())
+    pub fn is_owned_by(&self, real_name: &str) -> bool {
+        self.real_name.eq_ignore_ascii_case(real_name)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.sink.strong_count() != 0
     }
 
-    pub fn coords_to_sink_channel_volumes(
-        emitter_pos: Vec3,
-        position: Vec3,
-        self_rot_yaw: f32,
-    ) -> Vec<f32> {
-        let my_pos = vec3_to_vector3(&position);
-        let my_forward = vec3_to_vector3(&Vec3::get_dir_vector(self_rot_yaw, 0.0));
+    /// Silences the sink and drops the emitter; see `stop_silenced` for
+    /// stopping the sink itself.
+    pub fn stop(self) {
+        self.silence();
+    }
+
+    fn silence(&self) {
+        if let Some(sink) = self.sink.upgrade() {
+            sink.set_channel_volumes(vec![0.0, 0.0]);
+        }
+    }
 
-        let ent_pos = vec3_to_vector3(&emitter_pos);
+    /// returns None to remove the emitter
+    pub fn update(
+        &mut self,
+        entities: &mut SyncShared<Entities>,
+        tick: &EmitterTick,
+    ) -> Option<()> {
+        let mut occlusion = Occlusion::OPEN;
+        let channel_volumes = if self.positional {
+            let emitter_pos = self.static_pos.or_else(|| {
+                let entity = entities.borrow_mut().get(self.entity_id)?;
+                let entity = entity.upgrade()?;
+                Some(entity.get_position())
+            })?;
+
+            let listener = tick.listener?;
+
+            if tick.occlusion {
+                if tick.check_occlusion || self.occlusion.is_none() {
+                    self.occlusion = Some(Occlusion::between(listener.position, emitter_pos));
+                }
+                occlusion = self.occlusion.unwrap_or(Occlusion::OPEN);
+            }
+
+            spatialize(&tick.spatial, &listener, emitter_pos)
+        } else {
+            vec![1.0, 1.0]
+        };
+
+        let sink = self.sink.upgrade()?;
+
+        let fade = tick.max_duration.map_or(1.0, |max_duration| {
+            fade_out(tick.now.duration_since(self.started), max_duration)
+        });
+        if fade <= 0.0 {
+            self.silence();
+            return None;
+        }
 
-        let relative_distance = relative_distance((my_pos - ent_pos).magnitude());
-        let relative_volume = 1.0 - relative_distance;
+        sink.set_channel_volumes(
+            channel_volumes
+                .into_iter()
+                .map(|channel_volume| channel_volume * self.volume * occlusion.gain * fade)
+                .collect(),
+        );
 
-        let up = Vector3::y();
-        let left = Vector3::cross(&my_forward, &up);
-        let left = left.normalize();
+        Some(())
+    }
+}
 
-        let pan = (ent_pos - my_pos).normalize().dot(&left);
+/// Stops and removes every sound played by `real_name`, returning how many
+/// were still playing.
+pub fn stop_owned_by(entity_emitters: &mut Vec<EntityEmitter>, real_name: &str) -> usize {
+    let mut stopped = 0;
+    for emitter in entity_emitters.extract_if(.., |emitter| emitter.is_owned_by(real_name)) {
+        if emitter.is_playing() {
+            stopped += 1;
+        }
+        emitter.stop();
+    }
+    stopped
+}
 
-        let frame = Frame::from_mono(relative_volume).panned(Panning(pan));
+/// Stops and removes the emitter playing `sink`.
+pub fn stop_sink(entity_emitters: &mut Vec<EntityEmitter>, sink: &Weak<ChannelVolumeSink>) {
+    for emitter in entity_emitters.extract_if(.., |emitter| emitter.sink.ptr_eq(sink)) {
+        emitter.stop();
+    }
+}
 
-        vec![
-            //
-            frame.left.clamp(0.0, 1.0),
-            frame.right.clamp(0.0, 1.0),
-        ]
+/// chatsounds can only stop every sound at once, so stopping one only
+/// silences its sink, which keeps decoding until it ends. Once none of the
+/// tracked sounds are still playing, this stops the silenced ones for real.
+///
+/// Every sound is tracked as an emitter, and they're pushed while the
+/// chatsounds lock is held, so none can start between the check and the stop.
+pub fn stop_silenced(chatsounds: &mut Chatsounds, entity_emitters: &mut Vec<EntityEmitter>) {
+    entity_emitters.retain(EntityEmitter::is_playing);
+    if entity_emitters.is_empty() {
+        chatsounds.stop_all();
     }
 }
 
-// https://docs.rs/kira/0.10.8/src/kira/track/sub/spatial_builder.rs.html#355
-fn relative_distance(distance: f32) -> f32 {
-    const MIN_DISTANCE: f32 = 3.0;
-    const MAX_DISTANCE: f32 = 30.0;
+/// `stop_silenced` for callers that don't hold the chatsounds lock.
+pub fn spawn_stop_silenced(
+    chatsounds: FutureShared<Option<Chatsounds>>,
+    entity_emitters: ThreadShared<Vec<EntityEmitter>>,
+) {
+    FuturesModule::spawn_future(async move {
+        if let Some(chatsounds) = chatsounds.lock().await.as_mut() {
+            stop_silenced(chatsounds, &mut entity_emitters.lock().unwrap());
+        }
+    });
+}
+
+/// 1 until `max_duration`, then linearly down to 0 over `FADE_OUT_DURATION`
+fn fade_out(elapsed: Duration, max_duration: Duration) -> f32 {
+    let over = elapsed.saturating_sub(max_duration);
+    1.0 - over.as_secs_f32() / FADE_OUT_DURATION.as_secs_f32()
+}
+
+#[test]
+fn test_fade_out() {
+    let max_duration = Duration::from_secs(10);
 
-    let distance = distance.clamp(MIN_DISTANCE, MAX_DISTANCE);
-    (distance - MIN_DISTANCE) / (MAX_DISTANCE - MIN_DISTANCE)
+    assert!((fade_out(Duration::ZERO, max_duration) - 1.0).abs() < f32::EPSILON);
+    assert!((fade_out(max_duration, max_duration) - 1.0).abs() < f32::EPSILON);
+    assert!(
+        (fade_out(max_duration + FADE_OUT_DURATION / 2, max_duration) - 0.5).abs() < f32::EPSILON
+    );
+    assert!(fade_out(max_duration + FADE_OUT_DURATION, max_duration) <= 0.0);
 }
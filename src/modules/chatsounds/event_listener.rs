@@ -1,21 +1,43 @@
+use std::time::Instant;
+
 use chatsounds::Chatsounds;
 use classicube_helpers::{
     entities::{ENTITY_SELF_ID, Entities},
     tab_list::{TabList, remove_color},
 };
-use classicube_sys::{MsgType, MsgType_MSG_TYPE_NORMAL, Server, Vec3, WindowInfo};
+use classicube_sys::{
+    Gui, InputButtons, InputButtons_CCKEY_LCTRL, InputButtons_CCKEY_LSHIFT,
+    InputButtons_CCKEY_RCTRL, InputButtons_CCKEY_RSHIFT, MsgType, MsgType_MSG_TYPE_NORMAL, Server,
+    Vec3, WindowInfo,
+};
+use tracing::debug;
 
-use super::{entity_emitter::EntityEmitter, random, send_entity::SendEntity};
+use super::{
+    entity_emitter::{
+        EmitterTick, EntityEmitter, spawn_stop_silenced, stop_owned_by, stop_silenced,
+    },
+    filter::SENTENCE_FILTER,
+    mute::MUTE_STATE,
+    occlusion::OCCLUSION_INTERVAL,
+    player_settings, random,
+    rate_limit::{RATE_LIMIT_SETTINGS, RATE_LIMITER},
+    recent::record_play,
+    send_entity::SendEntity,
+    soundboard::SOUNDBOARD,
+    spatializer::{Listener, SPATIAL_SETTINGS, spatialize},
+};
 use crate::{
     helpers::{
-        get_self_position_and_yaw, is_continuation_message, is_global_cs_message,
-        is_global_csent_message, is_global_cspos_message,
+        is_continuation_message, is_global_cs_message, is_global_csent_message,
+        is_global_cspos_message,
     },
     modules::{
         FutureShared, FuturesModule, OptionModule, SyncShared, ThreadShared,
         chatsounds::random::{GLOBAL_NAME, get_rng},
-        event_handler::{IncomingEvent, IncomingEventListener},
+        event_handler::{IncomingEvent, IncomingEventListener, chat_send},
+        option::{KEYBINDS, KeyAction},
     },
+    printer::status,
 };
 
 pub struct ChatsoundsEventListener {
@@ -23,7 +45,9 @@ pub struct ChatsoundsEventListener {
     chatsounds: FutureShared<Option<Chatsounds>>,
     entities: SyncShared<Entities>,
     entity_emitters: ThreadShared<Vec<EntityEmitter>>,
-    last_volume: FutureShared<Option<f32>>,
+    ctrl_held: [bool; 2],
+    shift_held: [bool; 2],
+    last_occlusion_check: Instant,
     tab_list: SyncShared<TabList>,
 }
 
@@ -31,18 +55,135 @@ impl ChatsoundsEventListener {
     pub fn new(
         chatsounds: FutureShared<Option<Chatsounds>>,
         entities: SyncShared<Entities>,
+        entity_emitters: ThreadShared<Vec<EntityEmitter>>,
         tab_list: SyncShared<TabList>,
     ) -> Self {
         Self {
             chat_last: None,
             chatsounds,
             entities,
-            entity_emitters: ThreadShared::default(),
-            last_volume: FutureShared::default(),
+            entity_emitters,
+            ctrl_held: [false; 2],
+            shift_held: [false; 2],
+            last_occlusion_check: Instant::now(),
             tab_list,
         }
     }
 
+    fn handle_held_keys(&mut self, key: InputButtons, down: bool) {
+        match key {
+            InputButtons_CCKEY_LCTRL => self.ctrl_held[0] = down,
+            InputButtons_CCKEY_RCTRL => self.ctrl_held[1] = down,
+            InputButtons_CCKEY_LSHIFT => self.shift_held[0] = down,
+            InputButtons_CCKEY_RSHIFT => self.shift_held[1] = down,
+            _ => {}
+        }
+    }
+
+    fn handle_key_down(&mut self, key: InputButtons) {
+        self.handle_held_keys(key, true);
+
+        // chat or a menu is open, so the key is being typed
+        if unsafe { !Gui.InputGrab.is_null() } {
+            return;
+        }
+
+        let ctrl = self.ctrl_held.contains(&true);
+        let shift = self.shift_held.contains(&true);
+        match KEYBINDS.lock().action(key, ctrl, shift) {
+            Some(KeyAction::StopAll) => {
+                self.stop_all();
+                return;
+            }
+            Some(KeyAction::Mute) => {
+                self.toggle_mute();
+                return;
+            }
+            _ => {}
+        }
+
+        let sentence = SOUNDBOARD
+            .lock()
+            .get(key, ctrl, shift)
+            .map(ToString::to_string);
+        if let Some(sentence) = sentence {
+            self.play_soundboard(sentence);
+        }
+    }
+
+    fn play_soundboard(&self, sentence: String) {
+        if !OptionModule::soundboard_local() {
+            chat_send(sentence);
+            return;
+        }
+
+        // sent lines are filtered when they come back through chat
+        if !SENTENCE_FILTER.lock().is_allowed(&sentence) {
+            status(format!("&e{sentence} is filtered"));
+            return;
+        }
+
+        let chatsounds = self.chatsounds.clone();
+        let entity_emitters = self.entity_emitters.clone();
+
+        FuturesModule::spawn_future(async move {
+            let mut chatsounds = chatsounds.lock().await;
+            let Some(chatsounds) = chatsounds.as_mut() else {
+                return;
+            };
+
+            if let Ok((sink, _played_chatsounds)) = chatsounds
+                .play_channel_volume(&sentence, get_rng(""), vec![1.0, 1.0])
+                .await
+            {
+                record_play(&sentence, "", ENTITY_SELF_ID, false);
+
+                // tracked so that stopping and the max duration apply
+                entity_emitters.lock().unwrap().push(EntityEmitter::new_2d(
+                    ENTITY_SELF_ID,
+                    String::new(),
+                    &sink,
+                    1.0,
+                ));
+            }
+        });
+    }
+
+    fn stop_all(&self) {
+        let chatsounds = self.chatsounds.clone();
+        let entity_emitters = self.entity_emitters.clone();
+
+        FuturesModule::spawn_future(async move {
+            if let Some(chatsounds) = chatsounds.lock().await.as_mut() {
+                chatsounds.stop_all();
+            }
+            entity_emitters.lock().unwrap().clear();
+
+            status("&eStopped all sounds");
+        });
+    }
+
+    fn toggle_mute(&self) {
+        let chatsounds = self.chatsounds.clone();
+
+        FuturesModule::spawn_future(async move {
+            let mut chatsounds = chatsounds.lock().await;
+            let Some(chatsounds) = chatsounds.as_mut() else {
+                return;
+            };
+
+            let mut mute = MUTE_STATE.lock();
+            mute.by_key = !mute.by_key;
+            mute.apply(chatsounds);
+
+            if mute.by_key {
+                status("&eMuted chatsounds");
+            } else {
+                status("&eUnmuted chatsounds");
+            }
+        });
+    }
+
     fn find_player_from_message(&mut self, mut full_msg: String) -> Option<(u8, String, String)> {
         if unsafe { Server.IsSinglePlayer } != 0 {
             // in singleplayer there is no tab list, even self id infos are null
@@ -100,6 +241,17 @@ impl ChatsoundsEventListener {
         }
     }
 
+    /// `cs` and `cspos` messages don't say who sent them, but `csent` ones
+    /// carry the sender's entity id.
+    fn real_name_of(&self, entity_id: u8) -> Option<String> {
+        self.tab_list
+            .borrow()
+            .get_all()
+            .iter()
+            .find_map(|(id, weak)| (*id == entity_id).then(|| weak.upgrade()).flatten())
+            .map(|entry| entry.get_real_name())
+    }
+
     // run this sync so that chat_last comes in order
     fn handle_chat_received(&mut self, full_msg: String, msg_type: MsgType) {
         if msg_type != MsgType_MSG_TYPE_NORMAL {
@@ -111,16 +263,19 @@ impl ChatsoundsEventListener {
             return;
         }
 
-        let Some((self_pos, self_rot_yaw)) = get_self_position_and_yaw() else {
+        let Some(listener) = Listener::current(SPATIAL_SETTINGS.lock().listener) else {
             return;
         };
 
-        let (id, real_name, said_text, static_pos) = if let Some(said_text) =
+        // global messages use one shared random seed so that everyone hears the
+        // same sound, but mutes and rate limits apply to whoever sent them
+        let (id, real_name, sender, said_text, static_pos) = if let Some(said_text) =
             is_global_cs_message(&full_msg)
         {
             (
                 ENTITY_SELF_ID,
                 GLOBAL_NAME.to_string(),
+                GLOBAL_NAME.to_string(),
                 said_text.to_string(),
                 None,
             )
@@ -128,61 +283,132 @@ impl ChatsoundsEventListener {
             (
                 ENTITY_SELF_ID,
                 GLOBAL_NAME.to_string(),
+                GLOBAL_NAME.to_string(),
                 said_text.to_string(),
                 Some(static_pos),
             )
         } else if let Some((said_text, entity_id)) = is_global_csent_message(&full_msg) {
+            let sender = self
+                .real_name_of(entity_id)
+                .unwrap_or_else(|| GLOBAL_NAME.to_string());
+
             (
                 entity_id,
                 GLOBAL_NAME.to_string(),
+                sender,
                 said_text.to_string(),
                 None,
             )
         } else if let Some((id, real_name, said_text)) = self.find_player_from_message(full_msg) {
-            (id, real_name, said_text, None)
+            (id, real_name.clone(), real_name, said_text, None)
         } else {
             return;
         };
 
         random::update_chat_count(&real_name);
 
-        if let Some(entity) = self.entities.borrow_mut().get(id).and_then(|e| e.upgrade()) {
-            // if entity is in our map
-            let colorless_text: String = remove_color(said_text).trim().to_string();
-
-            let send_entity = SendEntity::from(&entity);
-
-            let chatsounds = self.chatsounds.clone();
-            let entity_emitters = self.entity_emitters.clone();
-
-            // it doesn't matter if these are out of order so we just spawn
-            FuturesModule::spawn_future(async move {
-                play_chatsound(
-                    colorless_text,
-                    real_name,
-                    send_entity,
-                    self_pos,
-                    self_rot_yaw,
-                    chatsounds,
-                    entity_emitters,
-                    static_pos,
-                )
-                .await;
-            });
+        // after update_chat_count so that random stays in sync with other players
+        let player_volume = player_settings::get(&sender).effective_volume();
+        if player_volume == 0.0 {
+            return;
         }
+
+        // if entity is in our map
+        let Some(entity) = self.entities.borrow_mut().get(id).and_then(|e| e.upgrade()) else {
+            return;
+        };
+
+        let colorless_text: String = remove_color(said_text).trim().to_string();
+
+        let send_entity = SendEntity::from(&entity);
+
+        let chatsounds = self.chatsounds.clone();
+        let entity_emitters = self.entity_emitters.clone();
+
+        // it doesn't matter if these are out of order so we just spawn
+        FuturesModule::spawn_future(async move {
+            play_chatsound(
+                colorless_text,
+                real_name,
+                sender,
+                send_entity,
+                listener,
+                chatsounds,
+                entity_emitters,
+                static_pos,
+                player_volume,
+            )
+            .await;
+        });
     }
 }
 
+/// Checks the rate limits for a new sound, returning the indexes of old
+/// sounds to stop first, or None to drop it.
+fn check_rate_limit(
+    entity_emitters: &mut Vec<EntityEmitter>,
+    real_name: &str,
+    entity_id: u8,
+) -> Option<Vec<usize>> {
+    entity_emitters.retain(EntityEmitter::is_playing);
+
+    let active: Vec<u8> = entity_emitters
+        .iter()
+        .map(EntityEmitter::entity_id)
+        .collect();
+    RATE_LIMITER.lock().check(
+        &RATE_LIMIT_SETTINGS.lock(),
+        Instant::now(),
+        real_name,
+        entity_id,
+        &active,
+    )
+}
+
+/// Tracks a sound that started playing, counting it towards the rate limits
+/// and stopping old sounds to make room for it.
+fn start_emitter(
+    chatsounds: &mut Chatsounds,
+    entity_emitters: &ThreadShared<Vec<EntityEmitter>>,
+    emitter: EntityEmitter,
+) {
+    let settings = *RATE_LIMIT_SETTINGS.lock();
+    let now = Instant::now();
+
+    let mut entity_emitters = entity_emitters.lock().unwrap();
+    // other sounds may have started or finished while this one was loading
+    let Some(cut) = check_rate_limit(
+        &mut entity_emitters,
+        emitter.real_name(),
+        emitter.entity_id(),
+    ) else {
+        debug!(real_name = ?emitter.real_name(), "rate limited");
+        emitter.stop();
+        stop_silenced(chatsounds, &mut entity_emitters);
+        return;
+    };
+    for i in cut.into_iter().rev() {
+        entity_emitters.remove(i).stop();
+    }
+
+    let mut rate_limiter = RATE_LIMITER.lock();
+    rate_limiter.record(&settings, now, emitter.real_name());
+    rate_limiter.cleanup(&settings, now);
+
+    entity_emitters.push(emitter);
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn play_chatsound(
     sentence: String,
     real_name: String,
+    sender: String,
     entity: SendEntity,
-    self_pos: Vec3,
-    self_rot_yaw: f32,
+    listener: Listener,
     chatsounds: FutureShared<Option<Chatsounds>>,
     entity_emitters: ThreadShared<Vec<EntityEmitter>>,
     static_pos: Option<Vec3>,
+    player_volume: f32,
 ) {
     let mut chatsounds = chatsounds.lock().await;
     let chatsounds = chatsounds.as_mut().unwrap();
@@ -193,30 +419,67 @@ pub async fn play_chatsound(
     }
 
     if sentence.to_lowercase() == "sh" {
-        chatsounds.stop_all();
-        entity_emitters.lock().unwrap().clear();
+        if OptionModule::sh_self_only() {
+            let mut entity_emitters = entity_emitters.lock().unwrap();
+            stop_owned_by(&mut entity_emitters, &sender);
+            stop_silenced(chatsounds, &mut entity_emitters);
+        } else {
+            chatsounds.stop_all();
+            entity_emitters.lock().unwrap().clear();
+        }
+        return;
+    }
+
+    if !SENTENCE_FILTER.lock().is_allowed(&sentence) {
+        return;
+    }
+
+    // only lines that play something count towards the limits, so this is
+    // checked again once the sound has started
+    if check_rate_limit(&mut entity_emitters.lock().unwrap(), &sender, entity.id).is_none() {
+        debug!(?sender, "rate limited");
         return;
     }
 
+    record_play(&sentence, &real_name, entity.id, static_pos.is_some());
+
     if static_pos.is_none() && entity.id == ENTITY_SELF_ID {
         // if self entity, play 2d sound
-        let _ignore_error = chatsounds.play(&sentence, get_rng(&real_name)).await;
+        if let Ok((sink, _played_chatsounds)) = chatsounds
+            .play_channel_volume(
+                &sentence,
+                get_rng(&real_name),
+                vec![player_volume, player_volume],
+            )
+            .await
+        {
+            // tracked so that the max duration applies
+            start_emitter(
+                chatsounds,
+                &entity_emitters,
+                EntityEmitter::new_2d(entity.id, sender, &sink, player_volume),
+            );
+        }
     } else {
-        let channel_volumes = EntityEmitter::coords_to_sink_channel_volumes(
+        let channel_volumes = spatialize(
+            &SPATIAL_SETTINGS.lock(),
+            &listener,
             static_pos.unwrap_or(entity.pos),
-            self_pos,
-            self_rot_yaw,
-        );
+        )
+        .into_iter()
+        .map(|channel_volume| channel_volume * player_volume)
+        .collect();
 
         if let Ok((sink, _played_chatsounds)) = chatsounds
             .play_channel_volume(&sentence, get_rng(&real_name), channel_volumes)
             .await
         {
             // don't print other's errors
-            entity_emitters
-                .lock()
-                .unwrap()
-                .push(EntityEmitter::new(entity.id, &sink, static_pos));
+            start_emitter(
+                chatsounds,
+                &entity_emitters,
+                EntityEmitter::new(entity.id, sender, &sink, static_pos, player_volume),
+            );
         }
     }
 }
@@ -228,37 +491,63 @@ impl IncomingEventListener for ChatsoundsEventListener {
                 self.handle_chat_received(message, msg_type);
             }
 
+            IncomingEvent::InputDown(key, repeating) => {
+                if !repeating {
+                    self.handle_key_down(key);
+                }
+            }
+
+            IncomingEvent::InputUp(key, _repeating) => {
+                self.handle_held_keys(key, false);
+            }
+
             IncomingEvent::FocusChanged(focused) => {
                 let mute_lose_focus = OptionModule::mute_lose_focus();
 
                 if mute_lose_focus {
                     let chatsounds = self.chatsounds.clone();
-                    let last_volume = self.last_volume.clone();
 
                     FuturesModule::spawn_future(async move {
                         let mut chatsounds = chatsounds.lock().await;
-                        let chatsounds = chatsounds.as_mut().unwrap();
-
-                        let mut last_volume = last_volume.lock().await;
+                        let mut mute = MUTE_STATE.lock();
+                        mute.by_focus = !focused;
 
-                        if !focused {
-                            *last_volume = Some(chatsounds.volume());
-                            chatsounds.set_volume(0.0);
-                        } else if let Some(volume) = *last_volume {
-                            chatsounds.set_volume(volume);
+                        // otherwise it's applied once they've loaded
+                        if let Some(chatsounds) = chatsounds.as_mut() {
+                            mute.apply(chatsounds);
                         }
                     });
                 }
             }
 
             IncomingEvent::Tick => {
-                // update positions on emitters
+                // update positions and occlusion on emitters, and fade out ones over the max
+                // duration
+
+                let now = Instant::now();
+                let check_occlusion =
+                    now.duration_since(self.last_occlusion_check) >= OCCLUSION_INTERVAL;
+                if check_occlusion {
+                    self.last_occlusion_check = now;
+                }
+
+                let spatial = *SPATIAL_SETTINGS.lock();
+                let listener = Listener::current(spatial.listener);
+
+                let tick = EmitterTick {
+                    now,
+                    listener,
+                    max_duration: OptionModule::max_duration(),
+                    spatial,
+                    occlusion: OptionModule::occlusion(),
+                    check_occlusion,
+                };
 
                 let mut entity_emitters = self.entity_emitters.lock().unwrap();
 
                 let mut to_remove = Vec::with_capacity(entity_emitters.len());
                 for (i, emitter) in entity_emitters.iter_mut().enumerate() {
-                    if emitter.update(&mut self.entities).is_none() {
+                    if emitter.update(&mut self.entities, &tick).is_none() {
                         to_remove.push(i);
                     }
                 }
@@ -270,6 +559,11 @@ impl IncomingEventListener for ChatsoundsEventListener {
                             entity_emitters.remove(i);
                         }
                     }
+
+                    // faded out sounds are only silenced
+                    if !entity_emitters.iter().any(EntityEmitter::is_playing) {
+                        spawn_stop_silenced(self.chatsounds.clone(), self.entity_emitters.clone());
+                    }
                 }
             }
 
@@ -0,0 +1,103 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+
+pub const FAVORITES_FILE_PATH: &str = "plugins/chatsounds/favorites.txt";
+
+pub static FAVORITES: Mutex<Favorites> = Mutex::new(Favorites::new());
+
+/// Sentences in the order they were added, one per line in the file.
+pub struct Favorites {
+    sentences: Vec<String>,
+}
+
+impl Default for Favorites {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Favorites {
+    pub const fn new() -> Self {
+        Self {
+            sentences: Vec::new(),
+        }
+    }
+
+    /// A missing file is no favorites.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut text = String::new();
+        for sentence in &self.sentences {
+            text.push_str(sentence);
+            text.push('\n');
+        }
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut favorites = Self::default();
+        for line in text.lines() {
+            favorites.add(line.trim().to_string());
+        }
+        favorites
+    }
+
+    /// returns false if it's empty or already a favorite
+    pub fn add(&mut self, sentence: String) -> bool {
+        if sentence.is_empty() || self.contains(&sentence) {
+            return false;
+        }
+
+        self.sentences.push(sentence);
+        true
+    }
+
+    /// returns false if it wasn't a favorite
+    pub fn remove(&mut self, sentence: &str) -> bool {
+        let len = self.sentences.len();
+        self.sentences.retain(|other| other != sentence);
+        self.sentences.len() != len
+    }
+
+    pub fn contains(&self, sentence: &str) -> bool {
+        self.sentences.iter().any(|other| other == sentence)
+    }
+
+    pub fn sentences(&self) -> &[String] {
+        &self.sentences
+    }
+}
+
+#[test]
+fn test_favorites() {
+    let mut favorites = Favorites::new();
+    assert!(favorites.add("hello".to_string()));
+    assert!(favorites.add("world".to_string()));
+    assert!(!favorites.add("hello".to_string()));
+    assert!(!favorites.add(String::new()));
+    assert_eq!(favorites.sentences(), ["hello", "world"]);
+
+    assert!(favorites.remove("hello"));
+    assert!(!favorites.remove("hello"));
+    assert!(!favorites.contains("hello"));
+
+    let favorites = Favorites::parse("a\n\n b \na\n");
+    assert_eq!(favorites.sentences(), ["a", "b"]);
+}
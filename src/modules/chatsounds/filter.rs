@@ -0,0 +1,226 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+    sync::LazyLock,
+};
+
+use anyhow::{Result, bail};
+use chatsounds::normalize_sentence;
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
+
+use crate::modules::OptionModule;
+
+pub const BLOCKLIST_FILE_PATH: &str = "plugins/chatsounds/blocklist.txt";
+pub const ALLOWLIST_FILE_PATH: &str = "plugins/chatsounds/allowlist.txt";
+
+pub static SENTENCE_FILTER: LazyLock<Mutex<SentenceFilter>> =
+    LazyLock::new(|| Mutex::new(SentenceFilter::default()));
+
+/// `/regex/`, a glob with `*` or `?`, or else an exact sentence.
+///
+/// Every kind of pattern matches a run of whole words anywhere in a line,
+/// since a chat line can play several sounds; `^` and `$` in a regex still
+/// anchor to the whole line.
+pub struct Pattern {
+    text: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("empty pattern");
+        }
+
+        let inner = if let Some(regex) = text
+            .strip_prefix('/')
+            .and_then(|text| text.strip_suffix('/'))
+            .filter(|regex| !regex.is_empty())
+        {
+            regex.to_string()
+        } else if text.contains(['*', '?']) {
+            glob_to_regex(text)
+        } else {
+            let sentence = normalize(text);
+            if sentence.is_empty() {
+                bail!("{text:?} is empty after normalizing");
+            }
+            regex::escape(&sentence)
+        };
+
+        // checked alone first, so that an unbalanced pattern like `a)|(b`
+        // can't escape the word boundaries
+        Regex::new(&inner)?;
+        let regex = RegexBuilder::new(&format!(r"(?:^|\s)(?:{inner})(?:\s|$)"))
+            .case_insensitive(true)
+            .build()?;
+
+        Ok(Self {
+            text: text.to_string(),
+            regex,
+        })
+    }
+
+    fn matches(&self, sentence: &str) -> bool {
+        self.regex.is_match(sentence)
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn normalize(text: &str) -> String {
+    normalize_sentence(&text.to_lowercase())
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex
+}
+
+/// One pattern per line, blank lines and lines starting with `#` are ignored.
+#[derive(Default)]
+pub struct PatternList {
+    pub patterns: Vec<Pattern>,
+}
+
+impl PatternList {
+    /// A missing file is an empty list.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut text = String::new();
+        for pattern in &self.patterns {
+            text.push_str(&pattern.text);
+            text.push('\n');
+        }
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(Pattern::parse)
+            .collect::<Result<_>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// returns false if the pattern was already in the list
+    pub fn add(&mut self, pattern: Pattern) -> bool {
+        if self.patterns.iter().any(|other| other.text == pattern.text) {
+            return false;
+        }
+
+        self.patterns.push(pattern);
+        true
+    }
+
+    /// returns false if the pattern wasn't in the list
+    pub fn remove(&mut self, text: &str) -> bool {
+        let len = self.patterns.len();
+        self.patterns.retain(|pattern| pattern.text != text.trim());
+        self.patterns.len() != len
+    }
+}
+
+#[derive(Default)]
+pub struct SentenceFilter {
+    pub blocklist: PatternList,
+    pub allowlist: PatternList,
+}
+
+impl SentenceFilter {
+    pub fn load() -> Result<Self> {
+        Ok(Self {
+            blocklist: PatternList::load(BLOCKLIST_FILE_PATH)?,
+            allowlist: PatternList::load(ALLOWLIST_FILE_PATH)?,
+        })
+    }
+
+    /// Blocked if any blocklist pattern matches within the line; with
+    /// allowlist-only, also blocked unless an allowlist pattern does.
+    pub fn is_allowed(&self, sentence: &str) -> bool {
+        self.is_allowed_with(sentence, OptionModule::allowlist_only())
+    }
+
+    fn is_allowed_with(&self, sentence: &str, allowlist_only: bool) -> bool {
+        let sentence = normalize(sentence);
+
+        if self
+            .blocklist
+            .patterns
+            .iter()
+            .any(|pattern| pattern.matches(&sentence))
+        {
+            return false;
+        }
+
+        !allowlist_only
+            || self
+                .allowlist
+                .patterns
+                .iter()
+                .any(|pattern| pattern.matches(&sentence))
+    }
+}
+
+#[test]
+fn test_sentence_filter() {
+    let filter = SentenceFilter {
+        blocklist: PatternList::parse("# comment\n\nloud noise\nscream*\n/ear ?rape/\n").unwrap(),
+        allowlist: PatternList::parse("hello\nwelcome *").unwrap(),
+    };
+
+    assert!(filter.is_allowed_with("hello", false));
+    assert!(!filter.is_allowed_with("loud noise", false));
+    assert!(!filter.is_allowed_with("Loud Noise", false));
+    assert!(!filter.is_allowed_with("hello loud noise", false));
+    assert!(filter.is_allowed_with("loud noises", false));
+    assert!(!filter.is_allowed_with("screaming", false));
+    assert!(!filter.is_allowed_with("hello screaming", false));
+    assert!(!filter.is_allowed_with("i scream", false));
+    assert!(filter.is_allowed_with("ice cream", false));
+    assert!(!filter.is_allowed_with("earrape 2", false));
+    assert!(!filter.is_allowed_with("ear rape", false));
+    assert!(!filter.is_allowed_with("hello ear rape", false));
+    assert!(filter.is_allowed_with("fear rape", false));
+
+    assert!(filter.is_allowed_with("hello", true));
+    assert!(filter.is_allowed_with("welcome back", true));
+    assert!(filter.is_allowed_with("hello there", true));
+    assert!(!filter.is_allowed_with("goodbye", true));
+    assert!(!filter.is_allowed_with("hello loud noise", true));
+
+    assert!(Pattern::parse("  ").is_err());
+    assert!(Pattern::parse("/(/").is_err());
+}
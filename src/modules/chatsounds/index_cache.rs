@@ -0,0 +1,278 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::warn;
+
+pub const INDEX_CACHE_PATH: &str = "plugins/chatsounds/index-cache";
+
+/// Older cache entries are still used at startup, but get refreshed in the
+/// background afterwards.
+pub const INDEX_CACHE_MAX_AGE: Duration = Duration::from_hours(24);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Cache,
+    Network,
+}
+
+#[derive(Debug)]
+pub struct Fetched<T> {
+    pub data: T,
+    pub origin: Origin,
+    pub stale: bool,
+}
+
+/// Source indexes as chatsounds fetched and parsed them, saved on disk with
+/// the time they were fetched, so startup doesn't have to wait on GitHub.
+pub struct IndexCache {
+    dir: PathBuf,
+    max_age: Duration,
+}
+
+impl IndexCache {
+    pub fn new<P: Into<PathBuf>>(dir: P, max_age: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            max_age,
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let file_name: String = key
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        self.dir.join(format!("{file_name}.bin"))
+    }
+
+    /// returns the cached index and how long ago it was saved
+    fn read<T: DeserializeOwned>(&self, key: &str) -> Option<(T, Duration)> {
+        let data = fs::read(self.entry_path(key)).ok()?;
+        let (timestamp, bytes) = data.split_first_chunk::<8>()?;
+
+        let saved_at = UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(*timestamp));
+        let age = SystemTime::now()
+            .duration_since(saved_at)
+            .unwrap_or_default();
+
+        match rmp_serde::from_slice(bytes) {
+            Ok(index) => Some((index, age)),
+            Err(e) => {
+                // e.g. written by a version with a different index format
+                warn!(?key, ?e, "ignoring unreadable cached index");
+                None
+            }
+        }
+    }
+
+    fn write<T: Serialize>(&self, key: &str, index: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut data = timestamp.to_le_bytes().to_vec();
+        data.extend(rmp_serde::to_vec_named(index)?);
+
+        // write then rename so that a crash mid-write can't leave a truncated entry
+        let path = self.entry_path(key);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, data)?;
+        fs::rename(tmp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Always awaits `fetch`, replacing the cache entry on success.
+    pub async fn refresh<T, F>(&self, key: &str, fetch: F) -> Result<T>
+    where
+        T: Serialize,
+        F: Future<Output = Result<T>>,
+    {
+        let index = fetch.await?;
+        self.write(key, &index)?;
+
+        Ok(index)
+    }
+
+    /// Uses the cache entry if there is one, unless `force` is set; `fetch`
+    /// is only awaited when the cache can't be used.
+    ///
+    /// If `fetch` fails, any cache entry is returned anyway (marked stale) so
+    /// that sounds still work offline.
+    pub async fn get<T, F>(&self, key: &str, fetch: F, force: bool) -> Result<Fetched<T>>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T>>,
+    {
+        match (force, self.read(key)) {
+            (false, Some((data, age))) => Ok(Fetched {
+                data,
+                origin: Origin::Cache,
+                stale: age >= self.max_age,
+            }),
+
+            (_, cached) => match self.refresh(key, fetch).await {
+                Ok(data) => Ok(Fetched {
+                    data,
+                    origin: Origin::Network,
+                    stale: false,
+                }),
+
+                Err(e) => {
+                    let Some((data, _age)) = cached else {
+                        return Err(e);
+                    };
+
+                    warn!(?key, ?e, "fetch failed, using cached index");
+                    Ok(Fetched {
+                        data,
+                        origin: Origin::Cache,
+                        stale: true,
+                    })
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Serves `bodies` in order (repeating the last one), counting requests.
+    async fn serve(bodies: &'static [&'static str]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/index", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        let server_hits = hits.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let hit = server_hits.fetch_add(1, Ordering::SeqCst);
+                let body = bodies[hit.min(bodies.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+
+        (url, hits)
+    }
+
+    /// A URL that refuses connections, from a listener that was bound then
+    /// dropped.
+    async fn closed_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/index", listener.local_addr().unwrap());
+        drop(listener);
+        url
+    }
+
+    /// Stands in for chatsounds' fetch and parse.
+    async fn fetch(url: &str) -> Result<String> {
+        Ok(reqwest::get(url).await?.error_for_status()?.text().await?)
+    }
+
+    fn cache(name: &str, max_age: Duration) -> IndexCache {
+        let dir = std::env::temp_dir().join(format!(
+            "classicube-chatsounds-plugin-test-{}-{name}",
+            std::process::id()
+        ));
+        let _ignore_error = fs::remove_dir_all(&dir);
+        IndexCache::new(dir, max_age)
+    }
+
+    #[tokio::test]
+    async fn test_cache_miss_then_hit() {
+        let (url, hits) = serve(&["first"]).await;
+        let cache = cache("miss-then-hit", INDEX_CACHE_MAX_AGE);
+
+        let fetched = cache.get("key", fetch(&url), false).await.unwrap();
+        assert_eq!(fetched.data, "first");
+        assert_eq!(fetched.origin, Origin::Network);
+        assert!(!fetched.stale);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let fetched = cache.get("key", fetch(&url), false).await.unwrap();
+        assert_eq!(fetched.data, "first");
+        assert_eq!(fetched.origin, Origin::Cache);
+        assert!(!fetched.stale);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        let fetched = cache.get("key", fetch(&url), true).await.unwrap();
+        assert_eq!(fetched.origin, Origin::Network);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cache_stale_refresh() {
+        let (url, hits) = serve(&["old", "new"]).await;
+        let cache = cache("stale-refresh", Duration::ZERO);
+
+        cache.get("key", fetch(&url), false).await.unwrap();
+
+        let fetched = cache.get("key", fetch(&url), false).await.unwrap();
+        assert_eq!(fetched.data, "old");
+        assert_eq!(fetched.origin, Origin::Cache);
+        assert!(fetched.stale);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        assert_eq!(cache.refresh("key", fetch(&url)).await.unwrap(), "new");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let fetched = cache.get("key", fetch(&url), false).await.unwrap();
+        assert_eq!(fetched.data, "new");
+        assert_eq!(fetched.origin, Origin::Cache);
+    }
+
+    #[tokio::test]
+    async fn test_cache_offline_fallback() {
+        let (url, _hits) = serve(&["cached"]).await;
+        let offline_url = closed_url().await;
+        let cache = cache("offline-fallback", INDEX_CACHE_MAX_AGE);
+
+        assert!(
+            cache
+                .get::<String, _>("key", fetch(&offline_url), false)
+                .await
+                .is_err()
+        );
+
+        cache.get("key", fetch(&url), false).await.unwrap();
+
+        let fetched = cache.get("key", fetch(&offline_url), true).await.unwrap();
+        assert_eq!(fetched.data, "cached");
+        assert_eq!(fetched.origin, Origin::Cache);
+        assert!(fetched.stale);
+    }
+}
@@ -1,75 +1,87 @@
-mod entity_emitter;
+pub mod entity_emitter;
 mod event_listener;
+pub mod favorites;
+pub mod filter;
+mod index_cache;
+pub mod mute;
+mod occlusion;
+pub mod player_settings;
 pub mod random;
+pub mod rate_limit;
+pub mod recent;
 mod send_entity;
+pub mod sentence_index;
+pub mod soundboard;
+pub mod sources;
+pub mod spatializer;
+pub mod status;
+pub mod usage;
 
-use std::{fmt::Display, fs, path::Path, pin::Pin};
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
 
 use anyhow::{Result, bail};
-use chatsounds::Chatsounds;
+use chatsounds::{Chatsounds, normalize_sentence};
 use classicube_helpers::{entities::Entities, tab_list::TabList};
 use futures::prelude::*;
-use tracing::error;
+use tracing::{debug, error, warn};
 
-use self::event_listener::ChatsoundsEventListener;
-use super::{FutureShared, SyncShared};
+use self::{
+    entity_emitter::EntityEmitter,
+    event_listener::ChatsoundsEventListener,
+    favorites::{FAVORITES, FAVORITES_FILE_PATH, Favorites},
+    filter::{SENTENCE_FILTER, SentenceFilter},
+    index_cache::{INDEX_CACHE_MAX_AGE, INDEX_CACHE_PATH, IndexCache, Origin},
+    mute::MUTE_STATE,
+    rate_limit::{RATE_LIMIT_SETTINGS, RateLimitSettings},
+    sentence_index::{SENTENCE_INDEX, SentenceIndex},
+    soundboard::{SOUNDBOARD, Soundboard},
+    sources::{GitHubRepo, Source, SourcesConfig},
+    spatializer::{SPATIAL_SETTINGS, SpatialSettings},
+    status::{SOURCE_STATUSES, SourceStatus},
+    usage::{USAGE, USAGE_FILE_PATH, Usage},
+};
+use super::{FutureShared, SyncShared, ThreadShared};
 use crate::{
     modules::{
         EventHandlerModule, FuturesModule, Module, OptionModule, option::VOLUME_SETTING_NAME,
     },
-    printer::print,
+    printer::{print, status_forever},
 };
 
 pub const VOLUME_NORMAL: f32 = 0.1;
 
-#[derive(Debug)]
-struct GitHubRepo {
-    name: &'static str,
-    path: &'static str,
-}
+/// Files in a source's index that count as sounds.
+const SOUND_EXTENSIONS: &[&str] = &["ogg", "mp3", "wav", "flac"];
 
-impl Display for GitHubRepo {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "repo {}/{}", self.name, self.path)
-    }
-}
+static RELOADING: AtomicBool = AtomicBool::new(false);
 
-enum Source {
-    Api(GitHubRepo),
-    MsgPack(GitHubRepo),
-}
-impl Source {
-    const fn api(name: &'static str, path: &'static str) -> Source {
-        Source::Api(GitHubRepo { name, path })
-    }
+/// Holds `RELOADING` until dropped, so a reload task that panics doesn't
+/// block every later reload.
+struct ReloadingGuard;
 
-    const fn msgpack(name: &'static str, path: &'static str) -> Source {
-        Source::MsgPack(GitHubRepo { name, path })
+impl ReloadingGuard {
+    /// None if something is already reloading
+    fn acquire() -> Option<Self> {
+        (!RELOADING.swap(true, Ordering::AcqRel)).then_some(Self)
     }
 }
 
-const SOURCES: &[Source] = &[
-    Source::api("NotAwesome2/chatsounds", "sounds"),
-    Source::api(
-        "Metastruct/garrysmod-chatsounds",
-        "sound/chatsounds/autoadd",
-    ),
-    Source::api("PAC3-Server/chatsounds", "sounds/chatsounds"),
-    Source::api("MasterMenSilver/Astral-Dream-Things", "chatsounds"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "csgo"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "css"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "ep1"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "ep2"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "hl1"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "hl2"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "l4d"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "l4d2"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "portal"),
-    Source::msgpack("PAC3-Server/chatsounds-valve-games", "tf2"),
-];
+impl Drop for ReloadingGuard {
+    fn drop(&mut self) {
+        RELOADING.store(false, Ordering::Release);
+    }
+}
 
 pub struct ChatsoundsModule {
     pub chatsounds: FutureShared<Option<Chatsounds>>,
+    pub entity_emitters: ThreadShared<Vec<EntityEmitter>>,
     entities: SyncShared<Entities>,
     event_handler_module: SyncShared<EventHandlerModule>,
     tab_list: SyncShared<TabList>,
@@ -83,13 +95,14 @@ impl ChatsoundsModule {
     ) -> Self {
         Self {
             chatsounds: FutureShared::default(),
+            entity_emitters: ThreadShared::default(),
             entities,
             event_handler_module,
             tab_list,
         }
     }
 
-    pub(super) fn new_chatsounds() -> Result<Chatsounds> {
+    fn new_chatsounds() -> Result<Chatsounds> {
         if !fs::metadata("plugins")
             .map(|meta| meta.is_dir())
             .unwrap_or(false)
@@ -108,83 +121,388 @@ impl ChatsoundsModule {
         Ok(chatsounds)
     }
 
-    pub(super) async fn load_sources(chatsounds: &mut Chatsounds) -> Result<()> {
-        enum SourceData {
-            Api(chatsounds::GitHubApiTrees),
-            MsgPack(chatsounds::GitHubMsgpackEntries),
-        }
-
-        let stream: Pin<Box<dyn Stream<Item = _> + Send>> = Box::pin(
-            futures::stream::iter(SOURCES)
-                .map(|source| match source {
-                    Source::Api(repo) => chatsounds
-                        .fetch_github_api(repo.name, repo.path)
-                        .map_ok(SourceData::Api)
-                        .map(move |result| (repo, result))
-                        .boxed(),
-
-                    Source::MsgPack(repo) => chatsounds
-                        .fetch_github_msgpack(repo.name, repo.path)
-                        .map_ok(SourceData::MsgPack)
-                        .map(move |result| (repo, result))
-                        .boxed(),
+    /// Loads every enabled source, using cached indexes unless `force` is set.
+    ///
+    /// Returns the sources that were loaded from an outdated cache entry.
+    async fn load_sources(chatsounds: &mut Chatsounds, force: bool) -> Result<Vec<Source>> {
+        let sources: Vec<Source> = SourcesConfig::load()?.enabled().cloned().collect();
+        let cache = IndexCache::new(INDEX_CACHE_PATH, INDEX_CACHE_MAX_AGE);
+
+        let mut statuses: Vec<SourceStatus> =
+            sources.iter().cloned().map(SourceStatus::new).collect();
+        let total = sources.len();
+
+        let fetcher: &Chatsounds = chatsounds;
+        let mut stream: Pin<Box<dyn Stream<Item = _> + Send>> = Box::pin(
+            futures::stream::iter(sources.iter().enumerate())
+                .map(|(i, source)| {
+                    let cache = &cache;
+                    async move {
+                        let started = Instant::now();
+                        let result = fetch_source(fetcher, cache, source, force).await;
+                        (i, result, started.elapsed())
+                    }
                 })
                 .buffered(5),
         );
 
-        let fetched = stream.collect::<Vec<_>>().await;
+        let mut fetched = Vec::with_capacity(total);
+        while let Some(item) = stream.next().await {
+            fetched.push(item);
+            status_forever(format!("&eLoading sources {}/{total}", fetched.len()));
+        }
+        drop(stream);
 
-        for (repo, result) in fetched {
-            match result {
-                Ok(data) => match data {
-                    SourceData::Api(data) => {
-                        chatsounds.load_github_api(repo.name, repo.path, data)?;
-                    }
-                    SourceData::MsgPack(data) => {
-                        chatsounds.load_github_msgpack(repo.name, repo.path, data)?;
-                    }
-                },
+        let mut stale = Vec::new();
+        for (i, result, fetch_time) in fetched {
+            let status = &mut statuses[i];
+            status.fetch_time = Some(fetch_time);
 
-                Err(e) => {
-                    error!(?repo, ?e);
-                    print(format!("{}{} {}", classicube_helpers::color::RED, repo, e));
+            let result = result.and_then(|fetched| {
+                status.origin = Some(fetched.origin);
+                if fetched.stale {
+                    stale.push(sources[i].clone());
                 }
+                load_source_data(chatsounds, fetched.data)?;
+                Ok(fetched.sentences)
+            });
+            record_result(status, result);
+        }
+
+        *SOURCE_STATUSES.lock() = statuses;
+
+        Ok(stale)
+    }
+
+    /// Re-fetches `stale` sources, then swaps in a sound set built from the
+    /// refreshed cache.
+    async fn refresh_stale_sources(
+        chatsounds_option: FutureShared<Option<Chatsounds>>,
+        stale: Vec<Source>,
+    ) -> Result<()> {
+        // a reload swaps in freshly loaded sources anyway
+        let Some(_reloading) = ReloadingGuard::acquire() else {
+            debug!("already reloading, not refreshing stale sources");
+            return Ok(());
+        };
+
+        let cache = IndexCache::new(INDEX_CACHE_PATH, INDEX_CACHE_MAX_AGE);
+        let mut chatsounds = Self::new_chatsounds()?;
+
+        let mut refreshed = false;
+        for source in &stale {
+            match refresh_source(&chatsounds, &cache, source).await {
+                Ok(()) => refreshed = true,
+                Err(e) => warn!(?source, ?e, "couldn't refresh stale index"),
             }
         }
 
+        if refreshed {
+            let result = Self::load_sources(&mut chatsounds, false).await;
+            status_forever("");
+            result?;
+            Self::replace_chatsounds(&mut *chatsounds_option.lock().await, chatsounds);
+            debug!("refreshed {} stale sources", stale.len());
+        }
+
         Ok(())
     }
+
+    /// Builds a new sound set in the background while the current one keeps
+    /// playing, then swaps it in.
+    ///
+    /// Must not be called from inside `FuturesModule::block_future`, which
+    /// holds the runtime lock that spawning needs.
+    pub fn reload(chatsounds_option: FutureShared<Option<Chatsounds>>, force: bool) {
+        let Some(reloading) = ReloadingGuard::acquire() else {
+            print("&eChatsounds are already reloading");
+            return;
+        };
+
+        status_forever("&eReloading chatsounds...");
+
+        FuturesModule::spawn_future(async move {
+            let _reloading = reloading;
+
+            let future = async {
+                let mut chatsounds = Self::new_chatsounds()?;
+                Self::load_sources(&mut chatsounds, force).await?;
+                Ok::<_, anyhow::Error>(chatsounds)
+            };
+
+            match future.await {
+                Ok(chatsounds) => {
+                    Self::replace_chatsounds(&mut *chatsounds_option.lock().await, chatsounds);
+                    print("&aReloaded chatsounds");
+                }
+                Err(e) => {
+                    error!(?e);
+                    print(format!("{}{}", classicube_helpers::color::RED, e));
+                }
+            }
+
+            status_forever("");
+        });
+    }
+
+    /// Keeps the current volume, which may differ from the saved setting
+    /// (e.g. muted because the window lost focus).
+    fn replace_chatsounds(
+        chatsounds_option: &mut Option<Chatsounds>,
+        mut new_chatsounds: Chatsounds,
+    ) {
+        if let Some(old_chatsounds) = chatsounds_option.as_ref() {
+            new_chatsounds.set_volume(old_chatsounds.volume());
+        } else {
+            // muted before the first load finished
+            MUTE_STATE.lock().apply(&mut new_chatsounds);
+        }
+
+        *SENTENCE_INDEX.lock() = SentenceIndex::build(&new_chatsounds);
+        *chatsounds_option = Some(new_chatsounds);
+    }
+}
+
+enum SourceData<'a> {
+    Api(&'a GitHubRepo, chatsounds::GitHubApiTrees),
+    MsgPack(&'a GitHubRepo, chatsounds::GitHubMsgpackEntries),
+}
+
+struct FetchedSource<'a> {
+    data: SourceData<'a>,
+    sentences: usize,
+    origin: Origin,
+    /// whether it came from an outdated cache entry
+    stale: bool,
+}
+
+/// Fetches and parses the index through chatsounds, unless the cache has it.
+async fn fetch_source<'a>(
+    chatsounds: &Chatsounds,
+    cache: &IndexCache,
+    source: &'a Source,
+    force: bool,
+) -> Result<FetchedSource<'a>> {
+    let key = source.cache_key();
+
+    match source {
+        Source::Api(repo) => {
+            let fetched = cache
+                .get(&key, fetch_github_api(chatsounds, repo), force)
+                .await?;
+            let sentences = count_api_sentences(
+                &repo.path,
+                fetched.data.tree.iter().map(|entry| entry.path.as_str()),
+            );
+
+            Ok(FetchedSource {
+                data: SourceData::Api(repo, fetched.data),
+                sentences,
+                origin: fetched.origin,
+                stale: fetched.stale,
+            })
+        }
+
+        Source::MsgPack(repo) => {
+            let fetched = cache
+                .get(&key, fetch_github_msgpack(chatsounds, repo), force)
+                .await?;
+            let sentences = count_msgpack_sentences(
+                fetched
+                    .data
+                    .iter()
+                    .filter_map(|entry| entry.get(1))
+                    .map(String::as_str),
+            );
+
+            Ok(FetchedSource {
+                data: SourceData::MsgPack(repo, fetched.data),
+                sentences,
+                origin: fetched.origin,
+                stale: fetched.stale,
+            })
+        }
+    }
+}
+
+/// Always fetches the index, replacing the cache entry.
+async fn refresh_source(
+    chatsounds: &Chatsounds,
+    cache: &IndexCache,
+    source: &Source,
+) -> Result<()> {
+    let key = source.cache_key();
+
+    match source {
+        Source::Api(repo) => {
+            cache
+                .refresh(&key, fetch_github_api(chatsounds, repo))
+                .await?;
+        }
+        Source::MsgPack(repo) => {
+            cache
+                .refresh(&key, fetch_github_msgpack(chatsounds, repo))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_github_api(
+    chatsounds: &Chatsounds,
+    repo: &GitHubRepo,
+) -> Result<chatsounds::GitHubApiTrees> {
+    Ok(chatsounds.fetch_github_api(&repo.name, &repo.path).await?)
+}
+
+async fn fetch_github_msgpack(
+    chatsounds: &Chatsounds,
+    repo: &GitHubRepo,
+) -> Result<chatsounds::GitHubMsgpackEntries> {
+    Ok(chatsounds
+        .fetch_github_msgpack(&repo.name, &repo.path)
+        .await?)
+}
+
+/// Counts the sentences in a GitHub tree's file paths, which look like
+/// `<path>/<realm>/<sentence>.ogg` or `<path>/<realm>/<sentence>/<variant>.ogg`.
+///
+/// Counted per source since chatsounds doesn't expose counts, so a sentence
+/// in several sources counts for each of them.
+fn count_api_sentences<'a, I>(repo_path: &str, paths: I) -> usize
+where
+    I: Iterator<Item = &'a str>,
+{
+    let prefix = format!("{repo_path}/");
+
+    paths
+        .filter_map(|path| {
+            let path = path.strip_prefix(&prefix)?;
+            let (dir, file) = path.rsplit_once('/')?;
+            let (stem, ext) = file.rsplit_once('.')?;
+            if !SOUND_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) {
+                return None;
+            }
+
+            let name = match dir.split_once('/') {
+                Some((_realm, sentence)) => sentence,
+                None => stem,
+            };
+            Some(normalize_sentence(&name.to_lowercase()))
+        })
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Like `count_api_sentences`, for the sentence column of a msgpack list.
+fn count_msgpack_sentences<'a, I>(sentences: I) -> usize
+where
+    I: Iterator<Item = &'a str>,
+{
+    sentences
+        .map(|sentence| normalize_sentence(&sentence.to_lowercase()))
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+fn load_source_data(chatsounds: &mut Chatsounds, data: SourceData<'_>) -> Result<()> {
+    match data {
+        SourceData::Api(repo, data) => {
+            chatsounds.load_github_api(&repo.name, &repo.path, data)?;
+        }
+        SourceData::MsgPack(repo, data) => {
+            chatsounds.load_github_msgpack(&repo.name, &repo.path, data)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn record_result(status: &mut SourceStatus, result: Result<usize>) {
+    match result {
+        Ok(sentences) => {
+            status.sentences = sentences;
+        }
+
+        Err(e) => {
+            error!(source = ?status.source, ?e);
+            print(format!(
+                "{}{} {}",
+                classicube_helpers::color::RED,
+                status.source,
+                e
+            ));
+            status.last_error = Some(e.to_string());
+        }
+    }
 }
 
 impl Module for ChatsoundsModule {
     fn load(&mut self) {
         print(format!("Loading Chatsounds v{}", env!("CARGO_PKG_VERSION")));
 
+        // a reload task from before a Free/Init cycle died with the old runtime
+        RELOADING.store(false, Ordering::Release);
+
+        *RATE_LIMIT_SETTINGS.lock() = RateLimitSettings::load();
+        *SPATIAL_SETTINGS.lock() = SpatialSettings::load();
+        *SOUNDBOARD.lock() = Soundboard::load();
+
+        match SentenceFilter::load() {
+            Ok(sentence_filter) => *SENTENCE_FILTER.lock() = sentence_filter,
+            Err(e) => print(format!("{}{}", classicube_helpers::color::RED, e)),
+        }
+
+        match Favorites::load(FAVORITES_FILE_PATH) {
+            Ok(favorites) => *FAVORITES.lock() = favorites,
+            Err(e) => print(format!("{}{}", classicube_helpers::color::RED, e)),
+        }
+
+        match Usage::load(USAGE_FILE_PATH) {
+            Ok(usage) => *USAGE.lock() = usage,
+            Err(e) => print(format!("{}{}", classicube_helpers::color::RED, e)),
+        }
+
         let chatsounds_option = self.chatsounds.clone();
         FuturesModule::spawn_future(async move {
-            let mut chatsounds_option = chatsounds_option.lock().await;
+            let mut chatsounds_option_guard = chatsounds_option.lock().await;
 
             let future = async {
                 let mut chatsounds = ChatsoundsModule::new_chatsounds()?;
-                ChatsoundsModule::load_sources(&mut chatsounds).await?;
-                Ok::<_, anyhow::Error>(chatsounds)
+                let stale = ChatsoundsModule::load_sources(&mut chatsounds, false).await?;
+                Ok::<_, anyhow::Error>((chatsounds, stale))
             };
 
-            match future.await {
-                Ok(chatsounds) => {
-                    *chatsounds_option = Some(chatsounds);
+            let result = future.await;
+            status_forever("");
+
+            let stale = match result {
+                Ok((chatsounds, stale)) => {
+                    ChatsoundsModule::replace_chatsounds(&mut chatsounds_option_guard, chatsounds);
+                    stale
                 }
                 Err(e) => {
                     print(format!("{}{}", classicube_helpers::color::RED, e));
+                    return;
                 }
-            }
+            };
 
-            drop(chatsounds_option);
+            drop(chatsounds_option_guard);
+
+            // the cached sounds are already playable, so update them after
+            if !stale.is_empty()
+                && let Err(e) =
+                    ChatsoundsModule::refresh_stale_sources(chatsounds_option, stale).await
+            {
+                warn!(?e, "refreshing stale sources");
+            }
         });
 
         let chatsounds_event_listener = ChatsoundsEventListener::new(
             self.chatsounds.clone(),
             self.entities.clone(),
+            self.entity_emitters.clone(),
             self.tab_list.clone(),
         );
 
@@ -200,5 +518,26 @@ impl Module for ChatsoundsModule {
         });
     }
 
-    fn unload(&mut self) {}
+    fn unload(&mut self) {
+        if let Err(e) = USAGE.lock().save(USAGE_FILE_PATH) {
+            error!(?e, "saving usage");
+        }
+    }
+}
+
+#[test]
+fn test_count_sentences() {
+    let paths = [
+        "sounds",
+        "sounds/realm/hello.ogg",
+        "sounds/realm/Welcome Back/1.ogg",
+        "sounds/realm/welcome back/2.ogg",
+        "sounds/other realm/hello.mp3",
+        "sounds/realm/readme.txt",
+        "elsewhere/realm/boom.ogg",
+    ];
+    assert_eq!(count_api_sentences("sounds", paths.into_iter()), 2);
+
+    let sentences = ["hello", "hello", "Boom"];
+    assert_eq!(count_msgpack_sentences(sentences.into_iter()), 2);
 }
@@ -0,0 +1,53 @@
+use chatsounds::Chatsounds;
+use parking_lot::Mutex;
+
+pub static MUTE_STATE: Mutex<MuteState> = Mutex::new(MuteState::new());
+
+/// The mute key and losing focus both mute, so unmuting one of them mustn't
+/// bring the volume back while the other still applies.
+#[derive(Default)]
+pub struct MuteState {
+    pub by_key: bool,
+    pub by_focus: bool,
+    /// volume to go back to once nothing mutes
+    volume: Option<f32>,
+}
+
+impl MuteState {
+    pub const fn new() -> Self {
+        Self {
+            by_key: false,
+            by_focus: false,
+            volume: None,
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.by_key || self.by_focus
+    }
+
+    pub fn apply(&mut self, chatsounds: &mut Chatsounds) {
+        if self.is_muted() {
+            if self.volume.is_none() {
+                self.volume = Some(chatsounds.volume());
+            }
+            chatsounds.set_volume(0.0);
+        } else if let Some(volume) = self.volume.take() {
+            chatsounds.set_volume(volume);
+        }
+    }
+
+    /// The volume sounds play at once nothing mutes.
+    pub fn volume(&self, chatsounds: &Chatsounds) -> f32 {
+        self.volume.unwrap_or_else(|| chatsounds.volume())
+    }
+
+    /// While muted, only changes the volume that unmuting goes back to.
+    pub fn set_volume(&mut self, chatsounds: &mut Chatsounds, volume: f32) {
+        if let Some(saved) = &mut self.volume {
+            *saved = volume;
+        } else {
+            chatsounds.set_volume(volume);
+        }
+    }
+}
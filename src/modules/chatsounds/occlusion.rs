@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use classicube_sys::{Blocks, CollideType_COLLIDE_SOLID, Vec3, World};
+use ncollide3d::{
+    na::{Point3, Vector3},
+    query::Ray,
+};
+
+/// How often occlusion is re-checked for playing sounds.
+pub const OCCLUSION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// More blocks than this in the way doesn't make it any quieter.
+const MAX_OCCLUDING_BLOCKS: usize = 4;
+/// Gain multiplier per solid block in the way.
+const GAIN_PER_BLOCK: f32 = 0.6;
+
+/// How much quieter a sound is with blocks in the way.
+///
+/// This only turns the volume down: chatsounds has no way to filter a playing
+/// sink, so there's no muffling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occlusion {
+    pub gain: f32,
+}
+
+impl Occlusion {
+    pub const OPEN: Self = Self { gain: 1.0 };
+
+    pub fn from_blocks(blocks: usize) -> Self {
+        let blocks = i32::try_from(blocks.min(MAX_OCCLUDING_BLOCKS)).unwrap_or(i32::MAX);
+
+        Self {
+            gain: GAIN_PER_BLOCK.powi(blocks),
+        }
+    }
+
+    pub fn between(listener_pos: Vec3, emitter_pos: Vec3) -> Self {
+        Self::from_blocks(count_solid_blocks(
+            listener_pos,
+            emitter_pos,
+            world_block_is_solid,
+        ))
+    }
+}
+
+/// Counts the solid blocks a straight line from `from` to `to` passes through,
+/// not counting the blocks at either end (where the listener and the emitter
+/// are standing).
+///
+/// Walks the voxel grid one block at a time (Amanatides & Woo), so every block
+/// the line touches is visited exactly once.
+pub fn count_solid_blocks<F>(from: Vec3, to: Vec3, mut is_solid: F) -> usize
+where
+    F: FnMut(i32, i32, i32) -> bool,
+{
+    let origin = Point3::new(from.x, from.y, from.z);
+    let offset = Vector3::new(to.x - from.x, to.y - from.y, to.z - from.z);
+    let length = offset.magnitude();
+    if length <= f32::EPSILON {
+        return 0;
+    }
+    let ray = Ray::new(origin, offset / length);
+
+    #[allow(clippy::cast_possible_truncation)]
+    let block_of = |point: Point3<f32>| point.map(|c| c.floor() as i32);
+    let mut block = block_of(ray.origin);
+    let end = block_of(ray.point_at(length));
+
+    let mut step = [0; 3];
+    // distance along the ray to the next block boundary, per axis
+    let mut next = [f32::INFINITY; 3];
+    // distance along the ray between block boundaries, per axis
+    let mut delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        let dir = ray.dir[axis];
+        #[allow(clippy::cast_precision_loss)]
+        let block_start = block[axis] as f32;
+        if dir > 0.0 {
+            step[axis] = 1;
+            next[axis] = (block_start + 1.0 - ray.origin[axis]) / dir;
+            delta[axis] = 1.0 / dir;
+        } else if dir < 0.0 {
+            step[axis] = -1;
+            next[axis] = (block_start - ray.origin[axis]) / dir;
+            delta[axis] = -1.0 / dir;
+        }
+    }
+
+    let mut solid = 0;
+    loop {
+        let axis = if next[0] <= next[1] && next[0] <= next[2] {
+            0
+        } else if next[1] <= next[2] {
+            1
+        } else {
+            2
+        };
+        if next[axis] > length {
+            break;
+        }
+
+        block[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        if block == end {
+            break;
+        }
+        if is_solid(block.x, block.y, block.z) {
+            solid += 1;
+        }
+    }
+
+    solid
+}
+
+/// Outside the map counts as open air.
+pub fn world_block_is_solid(x: i32, y: i32, z: i32) -> bool {
+    unsafe {
+        if World.Blocks.is_null()
+            || x < 0
+            || y < 0
+            || z < 0
+            || x >= World.Width
+            || y >= World.Height
+            || z >= World.Length
+        {
+            return false;
+        }
+
+        let Ok(index) = usize::try_from((y * World.Length + z) * World.Width + x) else {
+            return false;
+        };
+        // like World_GetBlock: Blocks2 holds the high bits of extended block
+        // ids, and is the same array as Blocks when there aren't any
+        let block = (usize::from(*World.Blocks.add(index))
+            | (usize::from(*World.Blocks2.add(index)) << 8))
+            & usize::try_from(World.IDMask).unwrap_or(0xFF);
+
+        u32::from(Blocks.Collide[block]) == CollideType_COLLIDE_SOLID
+    }
+}
+
+#[test]
+fn test_count_solid_blocks() {
+    // a wall at x = 5
+    let wall = |x: i32, _y: i32, _z: i32| x == 5;
+
+    let listener = Vec3::new(0.5, 0.5, 0.5);
+    assert_eq!(
+        count_solid_blocks(listener, Vec3::new(9.5, 0.5, 0.5), wall),
+        1
+    );
+    assert_eq!(
+        count_solid_blocks(listener, Vec3::new(9.5, 2.3, 0.5), wall),
+        1
+    );
+    assert_eq!(
+        count_solid_blocks(listener, Vec3::new(4.5, 0.5, 0.5), wall),
+        0
+    );
+    assert_eq!(
+        count_solid_blocks(Vec3::new(9.5, 0.5, 0.5), listener, wall),
+        1
+    );
+
+    // standing inside solid blocks doesn't count
+    assert_eq!(
+        count_solid_blocks(Vec3::new(5.5, 0.5, 0.5), Vec3::new(5.5, 0.5, 0.7), wall),
+        0
+    );
+
+    // a 3 thick wall along a diagonal visits each block once
+    let thick = |x: i32, _y: i32, _z: i32| (3..6).contains(&x);
+    assert_eq!(
+        count_solid_blocks(listener, Vec3::new(9.5, 0.5, 0.5), thick),
+        3
+    );
+    let mut visited = Vec::new();
+    count_solid_blocks(listener, Vec3::new(9.5, 9.5, 9.5), |x, y, z| {
+        visited.push((x, y, z));
+        false
+    });
+    let mut deduped = visited.clone();
+    deduped.sort_unstable();
+    deduped.dedup();
+    assert_eq!(visited.len(), deduped.len());
+    assert!(visited.contains(&(5, 5, 5)));
+
+    assert_eq!(count_solid_blocks(listener, listener, |_, _, _| true), 0);
+}
+
+#[test]
+fn test_occlusion_from_blocks() {
+    assert_eq!(Occlusion::from_blocks(0), Occlusion::OPEN);
+
+    let one = Occlusion::from_blocks(1);
+    let two = Occlusion::from_blocks(2);
+    assert!(two.gain < one.gain && one.gain < 1.0);
+
+    assert_eq!(
+        Occlusion::from_blocks(MAX_OCCLUDING_BLOCKS),
+        Occlusion::from_blocks(100)
+    );
+}
@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use parking_lot::Mutex;
+
+use crate::modules::OptionModule;
+
+pub const PLAYER_MUTE_SETTING_PREFIX: &str = "chatsounds-player-mute-";
+pub const PLAYER_VOLUME_SETTING_PREFIX: &str = "chatsounds-player-volume-";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerSettings {
+    pub muted: bool,
+    pub volume: f32,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            volume: 1.0,
+        }
+    }
+}
+
+impl PlayerSettings {
+    /// 0 when muted, so callers only need to check one value
+    pub fn effective_volume(self) -> f32 {
+        if self.muted { 0.0 } else { self.volume }
+    }
+}
+
+// options are read lazily per player and cached here
+static PLAYER_SETTINGS: Mutex<BTreeMap<String, PlayerSettings>> = Mutex::new(BTreeMap::new());
+
+// ClassiCube option keys are case-insensitive, so the cache is too
+fn key(real_name: &str) -> String {
+    real_name.to_lowercase()
+}
+
+pub fn get<S: AsRef<str>>(real_name: S) -> PlayerSettings {
+    let key = key(real_name.as_ref());
+
+    let mut player_settings = PLAYER_SETTINGS.lock();
+    *player_settings.entry(key).or_insert_with_key(|key| {
+        let muted = OptionModule::get(format!("{PLAYER_MUTE_SETTING_PREFIX}{key}"))
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        let volume = OptionModule::get(format!("{PLAYER_VOLUME_SETTING_PREFIX}{key}"))
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+
+        PlayerSettings { muted, volume }
+    })
+}
+
+pub fn set_muted<S: AsRef<str>>(real_name: S, muted: bool) {
+    let key = key(real_name.as_ref());
+    // load the other saved value first so it isn't replaced by a default
+    get(&key);
+
+    // an empty value removes the option
+    OptionModule::set(
+        format!("{PLAYER_MUTE_SETTING_PREFIX}{key}"),
+        if muted {
+            "true".to_string()
+        } else {
+            String::new()
+        },
+    );
+
+    let mut player_settings = PLAYER_SETTINGS.lock();
+    player_settings.entry(key).or_default().muted = muted;
+}
+
+pub fn set_volume<S: AsRef<str>>(real_name: S, volume: f32) {
+    let key = key(real_name.as_ref());
+    // load the other saved value first so it isn't replaced by a default
+    get(&key);
+
+    OptionModule::set(
+        format!("{PLAYER_VOLUME_SETTING_PREFIX}{key}"),
+        if (volume - 1.0).abs() < f32::EPSILON {
+            String::new()
+        } else {
+            format!("{volume}")
+        },
+    );
+
+    let mut player_settings = PLAYER_SETTINGS.lock();
+    player_settings.entry(key).or_default().volume = volume;
+}
+
+/// Every player with a saved mute or volume, sorted by name.
+pub fn saved() -> Vec<(String, PlayerSettings)> {
+    let names: BTreeSet<String> = [PLAYER_MUTE_SETTING_PREFIX, PLAYER_VOLUME_SETTING_PREFIX]
+        .into_iter()
+        .flat_map(OptionModule::with_prefix)
+        .map(|(name, _)| name)
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let settings = get(&name);
+            (name, settings)
+        })
+        .filter(|(_, settings)| *settings != PlayerSettings::default())
+        .collect()
+}
@@ -0,0 +1,304 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Display},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Error, Result, bail};
+use parking_lot::Mutex;
+
+use crate::modules::OptionModule;
+
+pub const RATE_LIMIT_ENTITY_SETTING_NAME: &str = "chatsounds-ratelimit-entity";
+pub const RATE_LIMIT_WINDOW_SETTING_NAME: &str = "chatsounds-ratelimit-window";
+pub const RATE_LIMIT_TOTAL_SETTING_NAME: &str = "chatsounds-ratelimit-total";
+pub const RATE_LIMIT_MODE_SETTING_NAME: &str = "chatsounds-ratelimit-mode";
+
+pub static RATE_LIMIT_SETTINGS: Mutex<RateLimitSettings> = Mutex::new(RateLimitSettings::new());
+pub static RATE_LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter::new());
+
+/// What to do when a concurrency limit is hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// ignore the new sound
+    Drop,
+    /// stop the oldest sound to make room
+    Cut,
+}
+
+impl FromStr for RateLimitMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "drop" => Ok(Self::Drop),
+            "cut" => Ok(Self::Cut),
+            _ => bail!("expected drop or cut"),
+        }
+    }
+}
+
+impl Display for RateLimitMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Drop => write!(f, "drop"),
+            Self::Cut => write!(f, "cut"),
+        }
+    }
+}
+
+/// A limit of 0 disables that check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitSettings {
+    /// concurrent sounds per entity id
+    pub max_per_entity: usize,
+    /// sounds started per `window` per `real_name`
+    pub max_per_window: usize,
+    pub window: Duration,
+    /// concurrent emitters overall
+    pub max_total: usize,
+    pub mode: RateLimitMode,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimitSettings {
+    pub const fn new() -> Self {
+        Self {
+            max_per_entity: 0,
+            max_per_window: 0,
+            window: Duration::from_secs(5),
+            max_total: 0,
+            mode: RateLimitMode::Cut,
+        }
+    }
+
+    pub fn load() -> Self {
+        let default = Self::new();
+
+        let (max_per_window, window) = OptionModule::get(RATE_LIMIT_WINDOW_SETTING_NAME)
+            .and_then(|s| Self::parse_window(&s).ok())
+            .unwrap_or((default.max_per_window, default.window));
+
+        Self {
+            max_per_entity: OptionModule::get(RATE_LIMIT_ENTITY_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.max_per_entity),
+            max_per_window,
+            window,
+            max_total: OptionModule::get(RATE_LIMIT_TOTAL_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.max_total),
+            mode: OptionModule::get(RATE_LIMIT_MODE_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.mode),
+        }
+    }
+
+    pub fn save(&self) {
+        OptionModule::set(
+            RATE_LIMIT_ENTITY_SETTING_NAME,
+            format!("{}", self.max_per_entity),
+        );
+        OptionModule::set(RATE_LIMIT_WINDOW_SETTING_NAME, self.format_window());
+        OptionModule::set(RATE_LIMIT_TOTAL_SETTING_NAME, format!("{}", self.max_total));
+        OptionModule::set(RATE_LIMIT_MODE_SETTING_NAME, format!("{}", self.mode));
+    }
+
+    /// `<count>/<seconds>`, e.g. `6/5` for 6 sounds per 5 seconds
+    pub fn parse_window(s: &str) -> Result<(usize, Duration)> {
+        let Some((count, seconds)) = s.split_once('/') else {
+            bail!("expected <count>/<seconds>");
+        };
+
+        Ok((
+            count.trim().parse()?,
+            Duration::try_from_secs_f32(seconds.trim().parse()?)?,
+        ))
+    }
+
+    pub fn format_window(&self) -> String {
+        format!("{}/{}", self.max_per_window, self.window.as_secs_f32())
+    }
+}
+
+impl Display for RateLimitSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "entity {} window {} total {} mode {}",
+            self.max_per_entity,
+            self.format_window(),
+            self.max_total,
+            self.mode
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    started: BTreeMap<String, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub const fn new() -> Self {
+        Self {
+            started: BTreeMap::new(),
+        }
+    }
+
+    /// `active` holds the entity id of each playing emitter, oldest first.
+    ///
+    /// Returns None to drop the new sound, otherwise the indexes into
+    /// `active` to stop first. The window limit always drops, since cutting
+    /// an old sound wouldn't slow the speaker down.
+    ///
+    /// The sound only counts towards the window once it's passed to `record`.
+    pub fn check(
+        &self,
+        settings: &RateLimitSettings,
+        now: Instant,
+        real_name: &str,
+        entity_id: u8,
+        active: &[u8],
+    ) -> Option<Vec<usize>> {
+        let in_window = self.started.get(real_name).map_or(0, |started| {
+            started
+                .iter()
+                .filter(|&&time| now.duration_since(time) < settings.window)
+                .count()
+        });
+        if settings.max_per_window != 0 && in_window >= settings.max_per_window {
+            return None;
+        }
+
+        let mut cut = Vec::new();
+
+        let entity_active: Vec<usize> = active
+            .iter()
+            .enumerate()
+            .filter(|&(_, &id)| id == entity_id)
+            .map(|(i, _)| i)
+            .collect();
+        if settings.max_per_entity != 0 && entity_active.len() >= settings.max_per_entity {
+            if settings.mode == RateLimitMode::Drop {
+                return None;
+            }
+            let over = entity_active.len() + 1 - settings.max_per_entity;
+            cut.extend_from_slice(&entity_active[..over]);
+        }
+
+        let remaining = active.len() - cut.len();
+        if settings.max_total != 0 && remaining >= settings.max_total {
+            if settings.mode == RateLimitMode::Drop {
+                return None;
+            }
+            let over = remaining + 1 - settings.max_total;
+            let oldest: Vec<usize> = (0..active.len())
+                .filter(|i| !cut.contains(i))
+                .take(over)
+                .collect();
+            cut.extend(oldest);
+            cut.sort_unstable();
+        }
+
+        Some(cut)
+    }
+
+    /// Counts a sound from `real_name` that started playing.
+    pub fn record(&mut self, settings: &RateLimitSettings, now: Instant, real_name: &str) {
+        let started = self.started.entry(real_name.to_string()).or_default();
+        while started
+            .front()
+            .is_some_and(|&time| now.duration_since(time) >= settings.window)
+        {
+            started.pop_front();
+        }
+        started.push_back(now);
+    }
+
+    /// Forget speakers with nothing in their window, so names don't pile up.
+    pub fn cleanup(&mut self, settings: &RateLimitSettings, now: Instant) {
+        self.started.retain(|_, started| {
+            started
+                .back()
+                .is_some_and(|&time| now.duration_since(time) < settings.window)
+        });
+    }
+}
+
+#[test]
+fn test_rate_limiter() {
+    let settings = RateLimitSettings {
+        max_per_entity: 2,
+        max_per_window: 3,
+        window: Duration::from_secs(5),
+        max_total: 3,
+        mode: RateLimitMode::Cut,
+    };
+    let mut limiter = RateLimiter::default();
+    let now = Instant::now();
+
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[]), Some(vec![]));
+    // checking alone doesn't count towards the window
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[]), Some(vec![]));
+    limiter.record(&settings, now, "a");
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[1]), Some(vec![]));
+    limiter.record(&settings, now, "a");
+    // per entity: cut the oldest sound of entity 1
+    assert_eq!(
+        limiter.check(&settings, now, "a", 1, &[2, 1, 1]),
+        Some(vec![1])
+    );
+    limiter.record(&settings, now, "a");
+    // per window
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[]), None);
+    let later = now + Duration::from_secs(5);
+    assert_eq!(limiter.check(&settings, later, "a", 1, &[]), Some(vec![]));
+
+    // global: cut the oldest sounds overall
+    assert_eq!(
+        limiter.check(&settings, now, "b", 2, &[1, 3, 4]),
+        Some(vec![0])
+    );
+    assert_eq!(
+        limiter.check(&settings, now, "c", 2, &[2, 3, 2, 4]),
+        Some(vec![0, 1])
+    );
+
+    let settings = RateLimitSettings {
+        mode: RateLimitMode::Drop,
+        ..settings
+    };
+    let mut limiter = RateLimiter::default();
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[1, 1]), None);
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[2, 3, 4]), None);
+    assert_eq!(limiter.check(&settings, now, "a", 1, &[2, 3]), Some(vec![]));
+    limiter.record(&settings, now, "a");
+
+    limiter.cleanup(&settings, now);
+    assert!(!limiter.started.is_empty());
+    limiter.cleanup(&settings, later);
+    assert!(limiter.started.is_empty());
+
+    assert_eq!(
+        RateLimitSettings::parse_window("6/2.5").unwrap(),
+        (6, Duration::from_millis(2500))
+    );
+    assert!(RateLimitSettings::parse_window("6").is_err());
+
+    // nothing is limited unless it's turned on
+    let mut limiter = RateLimiter::default();
+    for _ in 0..100 {
+        limiter.record(&RateLimitSettings::new(), now, "a");
+    }
+    assert_eq!(
+        limiter.check(&RateLimitSettings::new(), now, "a", 1, &[1; 100]),
+        Some(vec![])
+    );
+}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+use chatsounds::normalize_sentence;
+use classicube_helpers::entities::ENTITY_SELF_ID;
+use parking_lot::Mutex;
+use tracing::debug;
+
+use super::{
+    random::GLOBAL_NAME,
+    usage::{USAGE, USAGE_FILE_PATH, unix_now},
+};
+
+/// How many sentences each list keeps.
+const MAX_RECENT: usize = 10;
+
+pub static RECENT: Mutex<Recent> = Mutex::new(Recent::new());
+
+/// Sentences played lately, newest first. These aren't saved.
+pub struct Recent {
+    /// by us
+    played: VecDeque<String>,
+    /// by anyone else
+    heard: VecDeque<String>,
+}
+
+impl Default for Recent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recent {
+    pub const fn new() -> Self {
+        Self {
+            played: VecDeque::new(),
+            heard: VecDeque::new(),
+        }
+    }
+
+    pub fn record_played(&mut self, sentence: &str) {
+        push_front(&mut self.played, sentence);
+    }
+
+    pub fn record_heard(&mut self, sentence: &str) {
+        push_front(&mut self.heard, sentence);
+    }
+
+    pub fn played(&self) -> &VecDeque<String> {
+        &self.played
+    }
+
+    pub fn heard(&self) -> &VecDeque<String> {
+        &self.heard
+    }
+}
+
+/// A sentence already in `list` moves to the front instead of repeating.
+fn push_front(list: &mut VecDeque<String>, sentence: &str) {
+    list.retain(|other| other != sentence);
+    list.push_front(sentence.to_string());
+    list.truncate(MAX_RECENT);
+}
+
+/// Our own sounds count towards usage, everyone else's towards popularity.
+/// Counts the play in usage and the recent lists.
+pub fn record_play(sentence: &str, real_name: &str, entity_id: u8, positioned: bool) {
+    let sentence = normalize_sentence(sentence);
+    let mut usage = USAGE.lock();
+
+    if entity_id == ENTITY_SELF_ID && !positioned && real_name != GLOBAL_NAME {
+        RECENT.lock().record_played(&sentence);
+        usage.record_sent(&sentence, unix_now());
+        // rare enough to save right away, so it survives a crash
+        if let Err(e) = usage.save(USAGE_FILE_PATH) {
+            debug!(?e, "saving usage");
+        }
+    } else {
+        RECENT.lock().record_heard(&sentence);
+        usage.record_heard(&sentence);
+    }
+}
+
+#[test]
+fn test_recent() {
+    let mut recent = Recent::new();
+    recent.record_played("hello");
+    recent.record_played("world");
+    recent.record_played("hello");
+    assert_eq!(recent.played(), &["hello", "world"]);
+    assert!(recent.heard().is_empty());
+
+    for i in 0..MAX_RECENT * 2 {
+        recent.record_heard(&format!("{i}"));
+    }
+    assert_eq!(recent.heard().len(), MAX_RECENT);
+    assert_eq!(recent.heard()[0], format!("{}", MAX_RECENT * 2 - 1));
+}
@@ -0,0 +1,38 @@
+use chatsounds::Chatsounds;
+use parking_lot::Mutex;
+
+/// Rebuilt whenever chatsounds are (re)loaded, so fuzzy autocomplete doesn't
+/// copy and lowercase every sentence on each keystroke.
+pub static SENTENCE_INDEX: Mutex<SentenceIndex> = Mutex::new(SentenceIndex::new());
+
+#[derive(Default)]
+pub struct SentenceIndex {
+    /// `(sentence, lowercased sentence)`
+    sentences: Vec<(String, String)>,
+}
+
+impl SentenceIndex {
+    pub const fn new() -> Self {
+        Self {
+            sentences: Vec::new(),
+        }
+    }
+
+    pub fn build(chatsounds: &Chatsounds) -> Self {
+        // an empty search matches every sentence
+        let sentences = chatsounds
+            .search("")
+            .iter()
+            .map(|(_pos, sentence)| (sentence.to_string(), sentence.to_ascii_lowercase()))
+            .collect();
+
+        Self { sentences }
+    }
+
+    /// `(sentence, lowercased sentence)` pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sentences
+            .iter()
+            .map(|(sentence, lowercase)| (sentence.as_str(), lowercase.as_str()))
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Result, bail};
+use classicube_sys::{Input_StorageNames, InputButtons, STRING_SIZE};
+use parking_lot::Mutex;
+
+use crate::modules::{OptionModule, option::KeyCombo};
+
+/// Longer sentences get cut off when read back from the options file.
+const MAX_SENTENCE_LEN: usize = STRING_SIZE as usize;
+
+pub static SOUNDBOARD: Mutex<Soundboard> = Mutex::new(Soundboard::new());
+
+/// Keys bound to sentences, each stored as a `chatsounds-bind-<key>` option
+/// like `chatsounds-bind-Ctrl+1`.
+pub struct Soundboard {
+    binds: Vec<(KeyCombo, String)>,
+}
+
+impl Default for Soundboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Soundboard {
+    pub const fn new() -> Self {
+        Self { binds: Vec::new() }
+    }
+
+    /// Tries every key with every combination of Ctrl and Shift.
+    pub fn load() -> Self {
+        let mut binds = Vec::new();
+
+        for i in 0..Input_StorageNames.len() {
+            let Ok(key) = InputButtons::try_from(i) else {
+                continue;
+            };
+
+            for (ctrl, shift) in [(false, false), (true, false), (false, true), (true, true)] {
+                let combo = KeyCombo { key, ctrl, shift };
+                if let Some(sentence) = OptionModule::get(setting_name(combo)) {
+                    binds.push((combo, sentence));
+                }
+            }
+        }
+
+        Self { binds }
+    }
+
+    pub fn bind(&mut self, combo: KeyCombo, sentence: String) -> Result<()> {
+        if sentence.trim().is_empty() {
+            bail!("expected a sentence");
+        }
+        if sentence.len() > MAX_SENTENCE_LEN {
+            bail!("sentences can be at most {MAX_SENTENCE_LEN} characters");
+        }
+
+        OptionModule::set(setting_name(combo), sentence.clone());
+
+        if let Some((_combo, old)) = self.binds.iter_mut().find(|(other, _)| *other == combo) {
+            *old = sentence;
+        } else {
+            self.binds.push((combo, sentence));
+        }
+
+        Ok(())
+    }
+
+    /// returns false if the key wasn't bound
+    pub fn unbind(&mut self, combo: KeyCombo) -> bool {
+        let len = self.binds.len();
+        self.binds.retain(|(other, _)| *other != combo);
+        if self.binds.len() == len {
+            return false;
+        }
+
+        OptionModule::set(setting_name(combo), String::new());
+        true
+    }
+
+    pub fn get(&self, key: InputButtons, ctrl: bool, shift: bool) -> Option<&str> {
+        self.binds
+            .iter()
+            .find(|(combo, _)| combo.matches(key, ctrl, shift))
+            .map(|(_combo, sentence)| sentence.as_str())
+    }
+
+    pub fn binds(&self) -> &[(KeyCombo, String)] {
+        &self.binds
+    }
+}
+
+fn setting_name(combo: KeyCombo) -> String {
+    format!("chatsounds-bind-{combo}")
+}
@@ -0,0 +1,256 @@
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Result, anyhow, bail};
+
+pub const SOURCES_FILE_PATH: &str = "plugins/chatsounds/sources.txt";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubRepo {
+    pub name: String,
+    pub path: String,
+}
+
+impl Display for GitHubRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "repo {}/{}", self.name, self.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Api(GitHubRepo),
+    MsgPack(GitHubRepo),
+}
+
+impl Source {
+    pub fn api<S: Into<String>>(name: S, path: S) -> Self {
+        Source::Api(GitHubRepo {
+            name: name.into(),
+            path: path.into(),
+        })
+    }
+
+    pub fn msgpack<S: Into<String>>(name: S, path: S) -> Self {
+        Source::MsgPack(GitHubRepo {
+            name: name.into(),
+            path: path.into(),
+        })
+    }
+
+    pub fn parse(kind: &str, name: &str, path: &str) -> Result<Self> {
+        match kind.to_ascii_lowercase().as_str() {
+            "api" => Ok(Source::api(name, path)),
+            "msgpack" => Ok(Source::msgpack(name, path)),
+            _ => bail!("unknown source kind {kind:?} (expected api or msgpack)"),
+        }
+    }
+
+    /// Names this source's entry in the index cache.
+    pub fn cache_key(&self) -> String {
+        let repo = self.repo();
+        format!("{} {} {}", self.kind(), repo.name, repo.path)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Source::Api(_) => "api",
+            Source::MsgPack(_) => "msgpack",
+        }
+    }
+
+    fn repo(&self) -> &GitHubRepo {
+        match self {
+            Source::Api(repo) | Source::MsgPack(repo) => repo,
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind(), self.repo())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceEntry {
+    pub source: Source,
+    pub enabled: bool,
+}
+
+fn default_sources() -> Vec<SourceEntry> {
+    [
+        Source::api("NotAwesome2/chatsounds", "sounds"),
+        Source::api(
+            "Metastruct/garrysmod-chatsounds",
+            "sound/chatsounds/autoadd",
+        ),
+        Source::api("PAC3-Server/chatsounds", "sounds/chatsounds"),
+        Source::api("MasterMenSilver/Astral-Dream-Things", "chatsounds"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "csgo"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "css"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "ep1"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "ep2"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "hl1"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "hl2"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "l4d"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "l4d2"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "portal"),
+        Source::msgpack("PAC3-Server/chatsounds-valve-games", "tf2"),
+    ]
+    .into_iter()
+    .map(|source| SourceEntry {
+        source,
+        enabled: true,
+    })
+    .collect()
+}
+
+/// One source per line: `<enabled|disabled> <api|msgpack> <repo> <path>`.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SourcesConfig {
+    pub entries: Vec<SourceEntry>,
+}
+
+impl SourcesConfig {
+    /// Falls back to the built-in defaults if the file doesn't exist yet.
+    pub fn load() -> Result<Self> {
+        let path = Path::new(SOURCES_FILE_PATH);
+        if !path.exists() {
+            return Ok(Self {
+                entries: default_sources(),
+            });
+        }
+
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Path::new(SOURCES_FILE_PATH);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let mut entries = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let [enabled, kind, name, path] = parts.as_slice() else {
+                bail!("{SOURCES_FILE_PATH} line {}: expected 4 columns", i + 1);
+            };
+
+            let enabled = match *enabled {
+                "enabled" => true,
+                "disabled" => false,
+                other => bail!(
+                    "{SOURCES_FILE_PATH} line {}: expected enabled or disabled, got {other:?}",
+                    i + 1
+                ),
+            };
+
+            entries.push(SourceEntry {
+                source: Source::parse(kind, name, path)?,
+                enabled,
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Source> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| &entry.source)
+    }
+
+    /// returns false if the source was already in the list
+    pub fn add(&mut self, source: Source) -> bool {
+        if self.entries.iter().any(|entry| entry.source == source) {
+            return false;
+        }
+
+        self.entries.push(SourceEntry {
+            source,
+            enabled: true,
+        });
+        true
+    }
+
+    /// `number` is 1-based, matching the output of `source list`
+    pub fn remove(&mut self, number: usize) -> Result<SourceEntry> {
+        if number == 0 || number > self.entries.len() {
+            return Err(anyhow!(
+                "no source #{number} (there are {})",
+                self.entries.len()
+            ));
+        }
+
+        Ok(self.entries.remove(number - 1))
+    }
+}
+
+impl Display for SourcesConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# <enabled|disabled> <api|msgpack> <repo> <path>")?;
+        for SourceEntry { source, enabled } in &self.entries {
+            let repo = source.repo();
+            writeln!(
+                f,
+                "{} {} {} {}",
+                if *enabled { "enabled" } else { "disabled" },
+                source.kind(),
+                repo.name,
+                repo.path
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sources_config_round_trip() {
+    let config = SourcesConfig {
+        entries: default_sources(),
+    };
+    assert_eq!(SourcesConfig::parse(&config.to_string()).unwrap(), config);
+}
+
+#[test]
+fn test_sources_config_parse() {
+    let config = SourcesConfig::parse(
+        "# comment\n\nenabled api Some/repo sounds\ndisabled msgpack Other/repo csgo\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.entries,
+        vec![
+            SourceEntry {
+                source: Source::api("Some/repo", "sounds"),
+                enabled: true,
+            },
+            SourceEntry {
+                source: Source::msgpack("Other/repo", "csgo"),
+                enabled: false,
+            },
+        ]
+    );
+    assert_eq!(config.enabled().count(), 1);
+
+    assert!(SourcesConfig::parse("enabled api Some/repo").is_err());
+    assert!(SourcesConfig::parse("maybe api Some/repo sounds").is_err());
+    assert!(SourcesConfig::parse("enabled ftp Some/repo sounds").is_err());
+}
@@ -0,0 +1,408 @@
+use std::{
+    f32::consts::FRAC_PI_4,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use anyhow::{Error, Result, bail};
+use classicube_sys::{Vec2, Vec3};
+use ncollide3d::na::Vector3;
+use parking_lot::Mutex;
+
+use crate::{
+    helpers::{
+        get_self_entity_position_and_orientation, get_self_position_and_orientation,
+        vec3_to_vector3,
+    },
+    modules::OptionModule,
+};
+
+pub const ATTENUATION_SETTING_NAME: &str = "chatsounds-attenuation";
+pub const MIN_DISTANCE_SETTING_NAME: &str = "chatsounds-min-distance";
+pub const MAX_DISTANCE_SETTING_NAME: &str = "chatsounds-max-distance";
+pub const LISTENER_SETTING_NAME: &str = "chatsounds-listener";
+
+pub static SPATIAL_SETTINGS: Mutex<SpatialSettings> = Mutex::new(SpatialSettings::new());
+
+/// Gain of a sound directly behind the listener, relative to one in front.
+const REAR_GAIN: f32 = 0.7;
+
+/// Gain at `max_distance` for `Attenuation::Exponential` (-40 dB).
+const EXPONENTIAL_MAX_DISTANCE_GAIN: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attenuation {
+    /// falls to silence at `max_distance`
+    Linear,
+    /// `min_distance / distance`, like real sound pressure
+    Inverse,
+    /// falls by the same factor every block, to -40 dB at `max_distance`
+    Exponential,
+}
+
+impl FromStr for Attenuation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "linear" => Ok(Self::Linear),
+            "inverse" => Ok(Self::Inverse),
+            "exponential" => Ok(Self::Exponential),
+            _ => bail!("expected linear, inverse or exponential"),
+        }
+    }
+}
+
+impl Display for Attenuation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Inverse => write!(f, "inverse"),
+            Self::Exponential => write!(f, "exponential"),
+        }
+    }
+}
+
+/// Where sounds are heard from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerMode {
+    /// the camera, so third-person and spectating hear from where they watch
+    Camera,
+    /// our own player entity, wherever the camera is
+    Player,
+    /// halfway between the camera and the player, facing where the camera is
+    Midpoint,
+}
+
+impl FromStr for ListenerMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "camera" => Ok(Self::Camera),
+            "player" => Ok(Self::Player),
+            "midpoint" => Ok(Self::Midpoint),
+            _ => bail!("expected camera, player or midpoint"),
+        }
+    }
+}
+
+impl Display for ListenerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Camera => write!(f, "camera"),
+            Self::Player => write!(f, "player"),
+            Self::Midpoint => write!(f, "midpoint"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialSettings {
+    pub attenuation: Attenuation,
+    /// full volume within this many blocks
+    pub min_distance: f32,
+    /// no further attenuation past this many blocks
+    pub max_distance: f32,
+    pub listener: ListenerMode,
+}
+
+impl Default for SpatialSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpatialSettings {
+    pub const fn new() -> Self {
+        Self {
+            attenuation: Attenuation::Linear,
+            min_distance: 3.0,
+            max_distance: 30.0,
+            listener: ListenerMode::Camera,
+        }
+    }
+
+    pub fn load() -> Self {
+        let default = Self::new();
+
+        let settings = Self {
+            attenuation: OptionModule::get(ATTENUATION_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.attenuation),
+            min_distance: OptionModule::get(MIN_DISTANCE_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.min_distance),
+            max_distance: OptionModule::get(MAX_DISTANCE_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.max_distance),
+            listener: OptionModule::get(LISTENER_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.listener),
+        };
+
+        if settings.validate().is_ok() {
+            settings
+        } else {
+            default
+        }
+    }
+
+    pub fn save(&self) {
+        OptionModule::set(ATTENUATION_SETTING_NAME, format!("{}", self.attenuation));
+        OptionModule::set(MIN_DISTANCE_SETTING_NAME, format!("{}", self.min_distance));
+        OptionModule::set(MAX_DISTANCE_SETTING_NAME, format!("{}", self.max_distance));
+        OptionModule::set(LISTENER_SETTING_NAME, format!("{}", self.listener));
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(self.min_distance > 0.0 && self.max_distance > self.min_distance) {
+            bail!("expected 0 < min distance < max distance");
+        }
+        Ok(())
+    }
+
+    /// 1 within `min_distance`, decreasing with distance until `max_distance`
+    pub fn gain(&self, distance: f32) -> f32 {
+        let distance = distance.clamp(self.min_distance, self.max_distance);
+        let t = (distance - self.min_distance) / (self.max_distance - self.min_distance);
+
+        match self.attenuation {
+            Attenuation::Linear => 1.0 - t,
+            Attenuation::Inverse => self.min_distance / distance,
+            Attenuation::Exponential => EXPONENTIAL_MAX_DISTANCE_GAIN.powf(t),
+        }
+    }
+}
+
+impl Display for SpatialSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} from {} to {} blocks, heard from {}",
+            self.attenuation, self.min_distance, self.max_distance, self.listener
+        )
+    }
+}
+
+/// Where we hear from; yaw and pitch are in radians, as from
+/// `Camera.GetOrientation`.
+#[derive(Debug, Clone, Copy)]
+pub struct Listener {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Listener {
+    pub fn current(mode: ListenerMode) -> Option<Self> {
+        Some(Self::from_views(
+            mode,
+            get_self_position_and_orientation()?,
+            get_self_entity_position_and_orientation(),
+        ))
+    }
+
+    /// `camera` and `player` are positions with yaw/pitch; without a player
+    /// entity the camera is used.
+    pub fn from_views(
+        mode: ListenerMode,
+        camera: (Vec3, Vec2),
+        player: Option<(Vec3, Vec2)>,
+    ) -> Self {
+        let (camera_position, camera_orientation) = camera;
+        let (player_position, player_orientation) = player.unwrap_or(camera);
+
+        let (position, orientation) = match mode {
+            ListenerMode::Camera => (camera_position, camera_orientation),
+            ListenerMode::Player => (player_position, player_orientation),
+            ListenerMode::Midpoint => (
+                Vec3::new(
+                    f32::midpoint(camera_position.x, player_position.x),
+                    f32::midpoint(camera_position.y, player_position.y),
+                    f32::midpoint(camera_position.z, player_position.z),
+                ),
+                camera_orientation,
+            ),
+        };
+
+        Self {
+            position,
+            yaw: orientation.x,
+            pitch: orientation.y,
+        }
+    }
+
+    /// same as `Vec3_GetDirVector`
+    fn forward(&self) -> Vector3<f32> {
+        Vector3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            -self.pitch.sin(),
+            -self.pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    fn right(&self) -> Vector3<f32> {
+        // from yaw only, so it's still defined when looking straight up/down
+        Vector3::new(self.yaw.cos(), 0.0, self.yaw.sin())
+    }
+}
+
+/// Left and right channel volumes for a sound at `emitter_pos`.
+///
+/// Distance gives the gain from `settings`, the side gives an equal-power pan,
+/// and sounds behind where the listener is looking (including above or below,
+/// depending on pitch) are a bit quieter.
+pub fn spatialize(settings: &SpatialSettings, listener: &Listener, emitter_pos: Vec3) -> Vec<f32> {
+    let offset = vec3_to_vector3(&emitter_pos) - vec3_to_vector3(&listener.position);
+    let distance = offset.magnitude();
+    let gain = settings.gain(distance);
+
+    let (pan, rear_gain) = if distance > f32::EPSILON {
+        let direction = offset / distance;
+
+        let pan = direction.dot(&listener.right());
+        let behind = (-direction.dot(&listener.forward())).max(0.0);
+        (pan, 1.0 - behind * (1.0 - REAR_GAIN))
+    } else {
+        (0.0, 1.0)
+    };
+
+    // -1 is full left, 1 is full right
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+
+    vec![
+        (angle.cos() * gain * rear_gain).clamp(0.0, 1.0),
+        (angle.sin() * gain * rear_gain).clamp(0.0, 1.0),
+    ]
+}
+
+#[cfg(test)]
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-4, "{a} != {b}");
+}
+
+#[test]
+fn test_attenuation() {
+    for attenuation in [
+        Attenuation::Linear,
+        Attenuation::Inverse,
+        Attenuation::Exponential,
+    ] {
+        let settings = SpatialSettings {
+            attenuation,
+            ..SpatialSettings::new()
+        };
+        assert_close(settings.gain(0.0), 1.0);
+        assert_close(settings.gain(settings.min_distance), 1.0);
+        assert!(settings.gain(10.0) < 1.0);
+        assert!(settings.gain(20.0) < settings.gain(10.0));
+        assert_close(settings.gain(1000.0), settings.gain(settings.max_distance));
+    }
+
+    let mut settings = SpatialSettings::new();
+    assert_close(settings.gain(30.0), 0.0);
+    settings.attenuation = Attenuation::Inverse;
+    assert_close(settings.gain(30.0), 0.1);
+    settings.attenuation = Attenuation::Exponential;
+    assert_close(settings.gain(30.0), 0.01);
+
+    assert!(
+        SpatialSettings {
+            min_distance: 5.0,
+            max_distance: 5.0,
+            ..settings
+        }
+        .validate()
+        .is_err()
+    );
+}
+
+#[test]
+fn test_spatialize() {
+    let settings = SpatialSettings::new();
+    let listener = Listener {
+        position: Vec3::new(0.0, 0.0, 0.0),
+        yaw: 0.0,
+        pitch: 0.0,
+    };
+
+    // in front: centered, equal power
+    let volumes = spatialize(&settings, &listener, Vec3::new(0.0, 0.0, -2.0));
+    assert_close(volumes[0], volumes[1]);
+    assert_close(volumes[0].powi(2) + volumes[1].powi(2), 1.0);
+
+    // on top of the listener
+    let volumes = spatialize(&settings, &listener, listener.position);
+    assert_close(volumes[0], volumes[1]);
+
+    // right and left
+    let volumes = spatialize(&settings, &listener, Vec3::new(2.0, 0.0, 0.0));
+    assert_close(volumes[0], 0.0);
+    assert_close(volumes[1], 1.0);
+    let volumes = spatialize(&settings, &listener, Vec3::new(-2.0, 0.0, 0.0));
+    assert_close(volumes[0], 1.0);
+    assert_close(volumes[1], 0.0);
+
+    // turning around swaps sides
+    let turned = Listener {
+        yaw: std::f32::consts::PI,
+        ..listener
+    };
+    let volumes = spatialize(&settings, &turned, Vec3::new(2.0, 0.0, 0.0));
+    assert_close(volumes[0], 1.0);
+
+    // behind is quieter than in front
+    let front = spatialize(&settings, &listener, Vec3::new(0.0, 0.0, -2.0));
+    let behind = spatialize(&settings, &listener, Vec3::new(0.0, 0.0, 2.0));
+    assert!(behind[0] < front[0]);
+
+    // pitch: a sound above is in front when looking up, behind when looking down
+    let above = Vec3::new(0.0, 2.0, 0.0);
+    let looking_up = Listener {
+        pitch: -std::f32::consts::FRAC_PI_2,
+        ..listener
+    };
+    let looking_down = Listener {
+        pitch: std::f32::consts::FRAC_PI_2,
+        ..listener
+    };
+    let up = spatialize(&settings, &looking_up, above);
+    let down = spatialize(&settings, &looking_down, above);
+    assert!(down[0] < up[0]);
+
+    // distance
+    let far = spatialize(&settings, &listener, Vec3::new(0.0, 0.0, -20.0));
+    assert!(far[0] < front[0]);
+    let too_far = spatialize(&settings, &listener, Vec3::new(0.0, 0.0, -40.0));
+    assert_close(too_far[0], 0.0);
+}
+
+#[test]
+fn test_listener_mode() {
+    let camera = (Vec3::new(0.0, 4.0, 0.0), Vec2 { x: 1.0, y: 0.5 });
+    let player = (Vec3::new(2.0, 0.0, 0.0), Vec2 { x: 2.0, y: 0.0 });
+
+    let listener = Listener::from_views(ListenerMode::Camera, camera, Some(player));
+    assert_close(listener.position.y, 4.0);
+    assert_close(listener.yaw, 1.0);
+
+    let listener = Listener::from_views(ListenerMode::Player, camera, Some(player));
+    assert_close(listener.position.x, 2.0);
+    assert_close(listener.yaw, 2.0);
+
+    let listener = Listener::from_views(ListenerMode::Midpoint, camera, Some(player));
+    assert_close(listener.position.x, 1.0);
+    assert_close(listener.position.y, 2.0);
+    assert_close(listener.yaw, 1.0);
+
+    // no player entity
+    let listener = Listener::from_views(ListenerMode::Player, camera, None);
+    assert_close(listener.position.y, 4.0);
+
+    assert_eq!(
+        "midpoint".parse::<ListenerMode>().unwrap(),
+        ListenerMode::Midpoint
+    );
+    assert!("eyes".parse::<ListenerMode>().is_err());
+}
@@ -0,0 +1,45 @@
+use std::{fmt::Write, time::Duration};
+
+use parking_lot::Mutex;
+
+use super::{index_cache::Origin, sources::Source};
+
+/// Results of the last `load_sources`, for `/client chatsounds sources`.
+pub static SOURCE_STATUSES: Mutex<Vec<SourceStatus>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone)]
+pub struct SourceStatus {
+    pub source: Source,
+    pub sentences: usize,
+    pub fetch_time: Option<Duration>,
+    pub origin: Option<Origin>,
+    pub last_error: Option<String>,
+}
+
+impl SourceStatus {
+    pub fn new(source: Source) -> Self {
+        Self {
+            source,
+            sentences: 0,
+            fetch_time: None,
+            origin: None,
+            last_error: None,
+        }
+    }
+
+    pub fn format(&self) -> String {
+        let mut line = format!("{} &f{} sentences", self.source, self.sentences);
+
+        if let Some(fetch_time) = self.fetch_time {
+            let _ignore_error = write!(line, " &7in {:.2}s", fetch_time.as_secs_f32());
+        }
+
+        match self.origin {
+            Some(Origin::Cache) => line.push_str(" &7from cache"),
+            Some(Origin::Network) => line.push_str(" &7from network"),
+            None => {}
+        }
+
+        line
+    }
+}
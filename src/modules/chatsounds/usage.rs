@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+
+pub const USAGE_FILE_PATH: &str = "plugins/chatsounds/usage.txt";
+
+pub static USAGE: Mutex<Usage> = Mutex::new(Usage::new());
+
+/// Play count at which popularity and usage reach 0.5.
+const HALF_COUNT: f32 = 5.0;
+/// Seconds since last sent at which recency reaches 0.5.
+const RECENCY_HALF_LIFE: f32 = 60.0 * 60.0 * 24.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SentenceUsage {
+    /// times we played it
+    pub sent: u32,
+    /// times anyone else played it
+    pub heard: u32,
+    /// unix seconds we last played it
+    pub last_sent: u64,
+}
+
+/// Each of these is from 0 to 1.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct UsageScore {
+    pub popularity: f32,
+    pub usage: f32,
+    pub recency: f32,
+}
+
+/// Per sentence play counts, one tab separated `sentence sent heard
+/// last_sent` line per sentence in the file.
+pub struct Usage {
+    sentences: BTreeMap<String, SentenceUsage>,
+}
+
+impl Default for Usage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Usage {
+    pub const fn new() -> Self {
+        Self {
+            sentences: BTreeMap::new(),
+        }
+    }
+
+    /// A missing file is no usage.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, self.format())?;
+
+        Ok(())
+    }
+
+    fn format(&self) -> String {
+        let mut text = String::new();
+        for (sentence, usage) in &self.sentences {
+            let _ignore_error = writeln!(
+                text,
+                "{sentence}\t{}\t{}\t{}",
+                usage.sent, usage.heard, usage.last_sent
+            );
+        }
+        text
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let mut sentences = BTreeMap::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut parts = line.rsplitn(4, '\t');
+            let (Some(last_sent), Some(heard), Some(sent), Some(sentence)) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+
+            sentences.insert(
+                sentence.to_string(),
+                SentenceUsage {
+                    sent: sent
+                        .parse()
+                        .with_context(|| format!("usage line {line:?}"))?,
+                    heard: heard
+                        .parse()
+                        .with_context(|| format!("usage line {line:?}"))?,
+                    last_sent: last_sent
+                        .parse()
+                        .with_context(|| format!("usage line {line:?}"))?,
+                },
+            );
+        }
+
+        Ok(Self { sentences })
+    }
+
+    pub fn record_sent(&mut self, sentence: &str, now: u64) {
+        let usage = self.sentences.entry(sentence.to_string()).or_default();
+        usage.sent = usage.sent.saturating_add(1);
+        usage.last_sent = now;
+    }
+
+    pub fn record_heard(&mut self, sentence: &str) {
+        let usage = self.sentences.entry(sentence.to_string()).or_default();
+        usage.heard = usage.heard.saturating_add(1);
+    }
+
+    pub fn get(&self, sentence: &str) -> SentenceUsage {
+        self.sentences.get(sentence).copied().unwrap_or_default()
+    }
+
+    pub fn score(&self, sentence: &str, now: u64) -> UsageScore {
+        let usage = self.get(sentence);
+
+        #[allow(clippy::cast_precision_loss)]
+        let saturate = |count: u32| count as f32 / (count as f32 + HALF_COUNT);
+
+        let recency = if usage.last_sent == 0 {
+            0.0
+        } else {
+            #[allow(clippy::cast_precision_loss)]
+            let age = now.saturating_sub(usage.last_sent) as f32;
+            0.5f32.powf(age / RECENCY_HALF_LIFE)
+        };
+
+        UsageScore {
+            popularity: saturate(usage.heard),
+            usage: saturate(usage.sent),
+            recency,
+        }
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[test]
+fn test_usage() {
+    let mut usage = Usage::new();
+    assert_eq!(usage.score("hello", 1000), UsageScore::default());
+
+    usage.record_sent("hello", 1000);
+    usage.record_heard("hello");
+    usage.record_heard("world");
+
+    let score = usage.score("hello", 1000);
+    assert!(score.usage > 0.0 && score.usage < 1.0);
+    assert!(score.popularity > 0.0);
+    assert!((score.recency - 1.0).abs() < 1e-4);
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let a_day_later = 1000 + RECENCY_HALF_LIFE as u64;
+    assert!((usage.score("hello", a_day_later).recency - 0.5).abs() < 1e-4);
+    assert!(usage.score("world", 1000).usage.abs() < f32::EPSILON);
+
+    let parsed = Usage::parse(&usage.format()).unwrap();
+    assert_eq!(parsed.get("hello"), usage.get("hello"));
+    assert_eq!(parsed.get("world").heard, 1);
+
+    assert!(Usage::parse("hello\tx\t1\t2").is_err());
+}
@@ -6,8 +6,9 @@ use std::{
     string::ToString,
 };
 
-use anyhow::{Result, anyhow};
-use chatsounds::Chatsounds;
+use anyhow::{Result, anyhow, bail};
+use chatsounds::{Chatsounds, normalize_sentence};
+use classicube_helpers::entities::ENTITY_SELF_ID;
 use classicube_sys::OwnedChatCommand;
 use tracing::error;
 
@@ -15,47 +16,165 @@ use crate::{
     is_plugin_active,
     modules::{
         EventHandlerModule, FutureShared, FuturesModule, Module, OptionModule, SyncShared,
-        chatsounds::{ChatsoundsModule, VOLUME_NORMAL, random::get_rng},
-        option::{AUTOCOMPLETE_SETTING_NAME, MUTE_LOSE_FOCUS_SETTING_NAME, VOLUME_SETTING_NAME},
+        ThreadShared,
+        chatsounds::{
+            ChatsoundsModule, VOLUME_NORMAL,
+            entity_emitter::{EntityEmitter, stop_owned_by, stop_silenced},
+            favorites::{FAVORITES, FAVORITES_FILE_PATH},
+            filter::{ALLOWLIST_FILE_PATH, BLOCKLIST_FILE_PATH, Pattern, SENTENCE_FILTER},
+            mute::MUTE_STATE,
+            player_settings,
+            random::get_rng,
+            rate_limit::{RATE_LIMIT_SETTINGS, RateLimitSettings},
+            recent::{RECENT, record_play},
+            soundboard::SOUNDBOARD,
+            sources::{SOURCES_FILE_PATH, Source, SourcesConfig},
+            spatializer::SPATIAL_SETTINGS,
+            status::SOURCE_STATUSES,
+        },
+        option::{
+            ALLOWLIST_ONLY_SETTING_NAME, AUTOCOMPLETE_MODE_SETTING_NAME,
+            AUTOCOMPLETE_PREVIEW_SETTING_NAME, AUTOCOMPLETE_SETTING_NAME,
+            HISTORY_DEDUPE_SETTING_NAME, KEYBINDS, KeyAction, KeyCombo, MAX_DURATION_SETTING_NAME,
+            MUTE_LOSE_FOCUS_SETTING_NAME, OCCLUSION_SETTING_NAME, SH_SELF_ONLY_SETTING_NAME,
+            SOUNDBOARD_LOCAL_SETTING_NAME, VOLUME_SETTING_NAME,
+        },
     },
     printer::print,
 };
 
+const ALLOWLIST_COMMAND_HELP: &str = "&a/client chatsounds allow|unallow <sentence|glob*|/regex/>";
+const ALLOWLIST_ONLY_COMMAND_HELP: &str =
+    "&a/client chatsounds allowlist-only [true|false] &e(Default false)";
 const AUTOCOMPLETE_COMMAND_HELP: &str =
     "&a/client chatsounds autocomplete [true|false] &e(Default true)";
+const AUTOCOMPLETE_MODE_COMMAND_HELP: &str =
+    "&a/client chatsounds autocomplete-mode [substring|fuzzy] &e(Default fuzzy)";
+const AUTOCOMPLETE_PREVIEW_COMMAND_HELP: &str =
+    "&a/client chatsounds autocomplete-preview [true|false] &e(Default false)";
+const BIND_COMMAND_HELP: &str = "&a/client chatsounds bind|unbind <key> [sentence] &e(e.g. Ctrl+1)";
+const BLOCKLIST_COMMAND_HELP: &str = "&a/client chatsounds block|unblock <sentence|glob*|/regex/>";
 const MUTE_LOSE_FOCUS_COMMAND_HELP: &str =
     "&a/client chatsounds mute-lose-focus [true|false] &e(Default true)";
-const PLAY_COMMAND_HELP: &str = "&a/client chatsounds play [text]";
-const RELOAD_COMMAND_HELP: &str = "&a/client chatsounds reload";
-const SH_COMMAND_HELP: &str = "&a/client chatsounds sh";
+const FAV_COMMAND_HELP: &str =
+    "&a/client chatsounds fav [list|add <sentence|#number>|remove <sentence|#number>]";
+const HISTORY_DEDUPE_COMMAND_HELP: &str =
+    "&a/client chatsounds history-dedupe [true|false] &e(Default true)";
+const KEY_COMMAND_HELP: &str = "&a/client chatsounds key \
+                                [next-hint|previous-hint|accept-hint|preview|stop-all|mute \
+                                <key|none>] &e(e.g. Ctrl+Space)";
+const MAX_DURATION_COMMAND_HELP: &str =
+    "&a/client chatsounds maxduration [seconds] &e(Default 0, no limit)";
+const MUTE_COMMAND_HELP: &str = "&a/client chatsounds mute|unmute <player>";
+const OCCLUSION_COMMAND_HELP: &str =
+    "&a/client chatsounds occlusion [true|false] &e(Default false, only lowers volume)";
+const PLAY_COMMAND_HELP: &str = "&a/client chatsounds play [text|#number]";
+const PLAYER_VOLUME_COMMAND_HELP: &str =
+    "&a/client chatsounds playervolume <player> [volume] &e(Default 1.0)";
+const RATE_LIMIT_COMMAND_HELP: &str = "&a/client chatsounds ratelimit [entity <n>|window \
+                                       <count>/<seconds>|total <n>|mode drop|cut] &e(Default 0, \
+                                       no limit)";
+const RECENT_COMMAND_HELP: &str = "&a/client chatsounds recent";
+const RELOAD_COMMAND_HELP: &str = "&a/client chatsounds reload [--force]";
+const SH_COMMAND_HELP: &str = "&a/client chatsounds sh [player]";
+const SH_SELF_ONLY_COMMAND_HELP: &str =
+    "&a/client chatsounds sh-self-only [true|false] &e(Default false)";
+const SOUNDBOARD_LOCAL_COMMAND_HELP: &str =
+    "&a/client chatsounds soundboard-local [true|false] &e(Default false)";
+const SOURCE_COMMAND_HELP: &str =
+    "&a/client chatsounds source [list|add api|msgpack <repo> <path>|remove <number>]";
+const SOURCES_COMMAND_HELP: &str = "&a/client chatsounds sources";
+const SPATIAL_COMMAND_HELP: &str = "&a/client chatsounds spatial [attenuation \
+                                    linear|inverse|exponential|distance <min> <max>|listener \
+                                    camera|player|midpoint]";
 const VOLUME_COMMAND_HELP: &str = "&a/client chatsounds volume [volume] &e(Default 1.0)";
 
 pub struct CommandModule {
     event_handler_module: SyncShared<EventHandlerModule>,
     chatsounds: FutureShared<Option<Chatsounds>>,
+    entity_emitters: ThreadShared<Vec<EntityEmitter>>,
+    /// set by the reload command; the reload is spawned once the command
+    /// callback's `block_future` has released the runtime
+    pending_reload: Option<bool>,
 }
 
 impl CommandModule {
     pub fn new(
         event_handler_module: SyncShared<EventHandlerModule>,
         chatsounds: FutureShared<Option<Chatsounds>>,
+        entity_emitters: ThreadShared<Vec<EntityEmitter>>,
     ) -> Self {
         Self {
             event_handler_module,
             chatsounds,
+            entity_emitters,
+            pending_reload: None,
         }
     }
 
     async fn command_callback(&mut self, args: Vec<String>) -> Result<()> {
         let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
 
-        if let ["reload"] = args.as_slice() {
-            let mut chatsounds_option = self.chatsounds.lock().await;
-            print("&eReloading chatsounds...");
-            let mut new_chatsounds = ChatsoundsModule::new_chatsounds()?;
-            ChatsoundsModule::load_sources(&mut new_chatsounds).await?;
-            *chatsounds_option = Some(new_chatsounds);
-            print("&aReloaded chatsounds");
+        if let ["reload", flags @ ..] = args.as_slice()
+            && matches!(flags, [] | ["--force"])
+        {
+            self.pending_reload = Some(!flags.is_empty());
+            return Ok(());
+        }
+
+        if let ["source", rest @ ..] = args.as_slice() {
+            return Self::source_command(rest);
+        }
+
+        if let [
+            command @ ("block" | "unblock" | "blocklist" | "allow" | "unallow" | "allowlist"),
+            rest @ ..,
+        ] = args.as_slice()
+        {
+            return Self::filter_command(command, &rest.join(" "));
+        }
+
+        if let ["ratelimit", rest @ ..] = args.as_slice() {
+            return Self::rate_limit_command(rest);
+        }
+
+        if let [command @ ("mute" | "unmute" | "playervolume"), rest @ ..] = args.as_slice() {
+            return Self::player_command(command, rest);
+        }
+
+        if let ["spatial", rest @ ..] = args.as_slice() {
+            return Self::spatial_command(rest);
+        }
+
+        if let ["fav", rest @ ..] = args.as_slice() {
+            return Self::fav_command(rest);
+        }
+
+        if let ["recent"] = args.as_slice() {
+            Self::recent_command();
+            return Ok(());
+        }
+
+        if let ["bind", rest @ ..] = args.as_slice() {
+            return Self::bind_command(rest);
+        }
+
+        if let ["unbind", combo] = args.as_slice() {
+            let combo: KeyCombo = combo.parse()?;
+            if SOUNDBOARD.lock().unbind(combo) {
+                print(format!("&eUnbound {combo}"));
+            } else {
+                print(format!("&e{combo} isn't bound"));
+            }
+            return Ok(());
+        }
+
+        if let ["key", rest @ ..] = args.as_slice() {
+            return Self::key_command(rest);
+        }
+
+        if let ["sources"] = args.as_slice() {
+            Self::sources_command();
             return Ok(());
         }
 
@@ -63,6 +182,22 @@ impl CommandModule {
         let chatsounds = chatsounds.as_mut().ok_or_else(|| anyhow!("no"))?;
 
         match args.as_slice() {
+            ["allowlist-only"] => {
+                let allowlist_only = OptionModule::allowlist_only();
+
+                print(format!(
+                    "{ALLOWLIST_ONLY_SETTING_NAME} (Currently {allowlist_only})"
+                ));
+            }
+
+            ["allowlist-only", allowlist_only] => {
+                let allowlist_only = allowlist_only.parse::<bool>()?;
+
+                OptionModule::set_allowlist_only(allowlist_only);
+
+                print(format!("&eSet allowlist-only to {allowlist_only}"));
+            }
+
             ["autocomplete"] => {
                 let autocomplete = OptionModule::autocomplete();
 
@@ -95,22 +230,165 @@ impl CommandModule {
                 print(format!("&eSet mute-lose-focus to {mute_lose_focus}"));
             }
 
+            ["autocomplete-mode"] => {
+                let autocomplete_mode = OptionModule::autocomplete_mode();
+
+                print(format!(
+                    "{AUTOCOMPLETE_MODE_SETTING_NAME} (Currently {autocomplete_mode})"
+                ));
+            }
+
+            ["autocomplete-mode", autocomplete_mode] => {
+                let autocomplete_mode = autocomplete_mode.parse()?;
+
+                OptionModule::set_autocomplete_mode(autocomplete_mode);
+
+                print(format!("&eSet autocomplete-mode to {autocomplete_mode}"));
+            }
+
+            ["autocomplete-preview"] => {
+                let autocomplete_preview = OptionModule::autocomplete_preview();
+
+                print(format!(
+                    "{AUTOCOMPLETE_PREVIEW_SETTING_NAME} (Currently {autocomplete_preview})"
+                ));
+            }
+
+            ["autocomplete-preview", autocomplete_preview] => {
+                let autocomplete_preview = autocomplete_preview.parse::<bool>()?;
+
+                OptionModule::set_autocomplete_preview(autocomplete_preview);
+
+                print(format!(
+                    "&eSet autocomplete-preview to {autocomplete_preview}"
+                ));
+            }
+
+            ["history-dedupe"] => {
+                let history_dedupe = OptionModule::history_dedupe();
+
+                print(format!(
+                    "{HISTORY_DEDUPE_SETTING_NAME} (Currently {history_dedupe})"
+                ));
+            }
+
+            ["history-dedupe", history_dedupe] => {
+                let history_dedupe = history_dedupe.parse::<bool>()?;
+
+                OptionModule::set_history_dedupe(history_dedupe);
+
+                print(format!("&eSet history-dedupe to {history_dedupe}"));
+            }
+
+            ["maxduration"] => {
+                let max_duration = OptionModule::max_duration().map_or_else(
+                    || "no limit".to_string(),
+                    |d| format!("{}s", d.as_secs_f32()),
+                );
+
+                print(format!(
+                    "{MAX_DURATION_SETTING_NAME} (Currently {max_duration})"
+                ));
+            }
+
+            ["maxduration", secs] => {
+                let secs = secs.parse::<f32>()?;
+
+                OptionModule::set_max_duration(secs)?;
+
+                print(format!("&eSet maxduration to {secs}"));
+            }
+
+            ["occlusion"] => {
+                let occlusion = OptionModule::occlusion();
+
+                print(format!("{OCCLUSION_SETTING_NAME} (Currently {occlusion})"));
+            }
+
+            ["occlusion", occlusion] => {
+                let occlusion = occlusion.parse::<bool>()?;
+
+                OptionModule::set_occlusion(occlusion);
+
+                print(format!("&eSet occlusion to {occlusion}"));
+            }
+
             ["play"] => {
                 print(PLAY_COMMAND_HELP);
             }
 
             ["play", words @ ..] => {
-                let text = words.join(" ");
+                let text = Self::resolve_numbered(&words.join(" "))?;
+                if !SENTENCE_FILTER.lock().is_allowed(&text) {
+                    bail!("{text} is filtered");
+                }
 
-                let _ignore_error = chatsounds.play(&text, get_rng("")).await;
+                if let Ok((sink, _played_chatsounds)) = chatsounds
+                    .play_channel_volume(&text, get_rng(""), vec![1.0, 1.0])
+                    .await
+                {
+                    record_play(&text, "", ENTITY_SELF_ID, false);
+
+                    // tracked so that stopping and the max duration apply
+                    self.entity_emitters
+                        .lock()
+                        .unwrap()
+                        .push(EntityEmitter::new_2d(
+                            ENTITY_SELF_ID,
+                            String::new(),
+                            &sink,
+                            1.0,
+                        ));
+                }
             }
 
             ["sh"] => {
                 chatsounds.stop_all();
+                self.entity_emitters.lock().unwrap().clear();
+            }
+
+            ["sh", real_name] => {
+                let mut entity_emitters = self.entity_emitters.lock().unwrap();
+                let stopped = stop_owned_by(&mut entity_emitters, real_name);
+                stop_silenced(chatsounds, &mut entity_emitters);
+
+                print(format!("&eStopped {stopped} sounds from {real_name}"));
+            }
+
+            ["soundboard-local"] => {
+                let soundboard_local = OptionModule::soundboard_local();
+
+                print(format!(
+                    "{SOUNDBOARD_LOCAL_SETTING_NAME} (Currently {soundboard_local})"
+                ));
+            }
+
+            ["soundboard-local", soundboard_local] => {
+                let soundboard_local = soundboard_local.parse::<bool>()?;
+
+                OptionModule::set_soundboard_local(soundboard_local);
+
+                print(format!("&eSet soundboard-local to {soundboard_local}"));
+            }
+
+            ["sh-self-only"] => {
+                let sh_self_only = OptionModule::sh_self_only();
+
+                print(format!(
+                    "{SH_SELF_ONLY_SETTING_NAME} (Currently {sh_self_only})"
+                ));
+            }
+
+            ["sh-self-only", sh_self_only] => {
+                let sh_self_only = sh_self_only.parse::<bool>()?;
+
+                OptionModule::set_sh_self_only(sh_self_only);
+
+                print(format!("&eSet sh-self-only to {sh_self_only}"));
             }
 
             ["volume"] => {
-                let current_volume = chatsounds.volume() / VOLUME_NORMAL;
+                let current_volume = MUTE_STATE.lock().volume(chatsounds) / VOLUME_NORMAL;
 
                 print(format!(
                     "{VOLUME_COMMAND_HELP} (Currently {current_volume})"
@@ -120,7 +398,9 @@ impl CommandModule {
             ["volume", volume] => {
                 let volume = volume.parse::<f32>()?;
 
-                chatsounds.set_volume(VOLUME_NORMAL * volume);
+                MUTE_STATE
+                    .lock()
+                    .set_volume(chatsounds, VOLUME_NORMAL * volume);
 
                 OptionModule::set(VOLUME_SETTING_NAME, format!("{volume}"));
 
@@ -128,12 +408,32 @@ impl CommandModule {
             }
 
             _ => {
-                let current_volume = chatsounds.volume() / VOLUME_NORMAL;
+                let current_volume = MUTE_STATE.lock().volume(chatsounds) / VOLUME_NORMAL;
+                print(ALLOWLIST_COMMAND_HELP);
+                print(ALLOWLIST_ONLY_COMMAND_HELP);
                 print(AUTOCOMPLETE_COMMAND_HELP);
+                print(AUTOCOMPLETE_MODE_COMMAND_HELP);
+                print(AUTOCOMPLETE_PREVIEW_COMMAND_HELP);
+                print(BIND_COMMAND_HELP);
+                print(BLOCKLIST_COMMAND_HELP);
+                print(FAV_COMMAND_HELP);
+                print(HISTORY_DEDUPE_COMMAND_HELP);
+                print(KEY_COMMAND_HELP);
+                print(MAX_DURATION_COMMAND_HELP);
+                print(MUTE_COMMAND_HELP);
                 print(MUTE_LOSE_FOCUS_COMMAND_HELP);
+                print(OCCLUSION_COMMAND_HELP);
                 print(PLAY_COMMAND_HELP);
+                print(PLAYER_VOLUME_COMMAND_HELP);
+                print(RATE_LIMIT_COMMAND_HELP);
+                print(RECENT_COMMAND_HELP);
                 print(RELOAD_COMMAND_HELP);
                 print(SH_COMMAND_HELP);
+                print(SH_SELF_ONLY_COMMAND_HELP);
+                print(SOUNDBOARD_LOCAL_COMMAND_HELP);
+                print(SOURCE_COMMAND_HELP);
+                print(SOURCES_COMMAND_HELP);
+                print(SPATIAL_COMMAND_HELP);
                 print(format!(
                     "{VOLUME_COMMAND_HELP} (Currently {current_volume})"
                 ));
@@ -142,6 +442,383 @@ impl CommandModule {
 
         Ok(())
     }
+
+    fn source_command(args: &[&str]) -> Result<()> {
+        let mut config = SourcesConfig::load()?;
+
+        match args {
+            ["list"] => {
+                for (i, entry) in config.entries.iter().enumerate() {
+                    let color = if entry.enabled { "&a" } else { "&7" };
+                    print(format!("{color}{}. {}", i + 1, entry.source));
+                }
+                print(format!(
+                    "&eEdit {SOURCES_FILE_PATH} to enable or disable sources"
+                ));
+            }
+
+            ["add", kind, name, path] => {
+                Self::add_source(&mut config, Source::parse(kind, name, path)?)?;
+            }
+
+            ["remove", number] => {
+                let entry = config.remove(number.parse()?)?;
+                config.save()?;
+                print(format!("&eRemoved {}", entry.source));
+                print(format!("&eRun {RELOAD_COMMAND_HELP} &eto apply"));
+            }
+
+            _ => {
+                print(SOURCE_COMMAND_HELP);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn filter_command(command: &str, pattern: &str) -> Result<()> {
+        let mut sentence_filter = SENTENCE_FILTER.lock();
+        let (list, path, help) = if command.contains("block") {
+            (
+                &mut sentence_filter.blocklist,
+                BLOCKLIST_FILE_PATH,
+                BLOCKLIST_COMMAND_HELP,
+            )
+        } else {
+            (
+                &mut sentence_filter.allowlist,
+                ALLOWLIST_FILE_PATH,
+                ALLOWLIST_COMMAND_HELP,
+            )
+        };
+
+        match command {
+            "blocklist" | "allowlist" => {
+                print(help);
+                for (i, pattern) in list.patterns.iter().enumerate() {
+                    print(format!("&a{}. {}", i + 1, pattern));
+                }
+            }
+
+            _ if pattern.is_empty() => {
+                print(help);
+            }
+
+            "block" | "allow" => {
+                if list.add(Pattern::parse(pattern)?) {
+                    list.save(path)?;
+                    print(format!("&eAdded {pattern} to {path}"));
+                } else {
+                    print(format!("&e{pattern} is already in {path}"));
+                }
+            }
+
+            _ => {
+                if list.remove(pattern) {
+                    list.save(path)?;
+                    print(format!("&eRemoved {pattern} from {path}"));
+                } else {
+                    print(format!("&e{pattern} is not in {path}"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn player_command(command: &str, args: &[&str]) -> Result<()> {
+        match (command, args) {
+            ("mute", []) => {
+                print(MUTE_COMMAND_HELP);
+                for (name, settings) in player_settings::saved() {
+                    if settings.muted {
+                        print(format!("&eMuted {name}"));
+                    }
+                }
+            }
+
+            ("mute", [real_name]) => {
+                player_settings::set_muted(real_name, true);
+
+                print(format!("&eMuted {real_name}"));
+            }
+
+            ("unmute", [real_name]) => {
+                player_settings::set_muted(real_name, false);
+
+                print(format!("&eUnmuted {real_name}"));
+            }
+
+            ("playervolume", []) => {
+                print(PLAYER_VOLUME_COMMAND_HELP);
+                for (name, settings) in player_settings::saved() {
+                    if (settings.volume - 1.0).abs() >= f32::EPSILON {
+                        print(format!("&e{name} volume {}", settings.volume));
+                    }
+                }
+            }
+
+            ("playervolume", [real_name]) => {
+                let volume = player_settings::get(real_name).volume;
+
+                print(format!(
+                    "{PLAYER_VOLUME_COMMAND_HELP} ({real_name} currently {volume})"
+                ));
+            }
+
+            ("playervolume", [real_name, volume]) => {
+                let volume = volume.parse::<f32>()?;
+                if !volume.is_finite() || volume < 0.0 {
+                    bail!("volume must be 0 or more");
+                }
+
+                player_settings::set_volume(real_name, volume);
+
+                print(format!("&eSet {real_name} volume to {volume}"));
+            }
+
+            ("mute" | "unmute", _) => print(MUTE_COMMAND_HELP),
+
+            _ => print(PLAYER_VOLUME_COMMAND_HELP),
+        }
+
+        Ok(())
+    }
+
+    fn rate_limit_command(args: &[&str]) -> Result<()> {
+        let mut settings = RATE_LIMIT_SETTINGS.lock();
+
+        match args {
+            ["entity", max] => settings.max_per_entity = max.parse()?,
+            ["window", window] => {
+                (settings.max_per_window, settings.window) =
+                    RateLimitSettings::parse_window(window)?;
+            }
+            ["total", max] => settings.max_total = max.parse()?,
+            ["mode", mode] => settings.mode = mode.parse()?,
+
+            _ => {
+                print(format!(
+                    "{RATE_LIMIT_COMMAND_HELP} (Currently {})",
+                    *settings
+                ));
+                return Ok(());
+            }
+        }
+
+        settings.save();
+        print(format!("&eSet rate limit to {}", *settings));
+
+        Ok(())
+    }
+
+    fn spatial_command(args: &[&str]) -> Result<()> {
+        let mut settings = *SPATIAL_SETTINGS.lock();
+
+        match args {
+            ["attenuation", attenuation] => settings.attenuation = attenuation.parse()?,
+            ["listener", listener] => settings.listener = listener.parse()?,
+            ["distance", min, max] => {
+                settings.min_distance = min.parse()?;
+                settings.max_distance = max.parse()?;
+            }
+
+            _ => {
+                print(format!("{SPATIAL_COMMAND_HELP} (Currently {settings})"));
+                return Ok(());
+            }
+        }
+
+        settings.validate()?;
+        settings.save();
+        *SPATIAL_SETTINGS.lock() = settings;
+        print(format!("&eSet spatial audio to {settings}"));
+
+        Ok(())
+    }
+
+    fn fav_command(args: &[&str]) -> Result<()> {
+        match args {
+            [] | ["list"] => {
+                let favorites = FAVORITES.lock();
+                if favorites.sentences().is_empty() {
+                    print("&eNo favorites yet");
+                }
+
+                for (i, sentence) in favorites.sentences().iter().enumerate() {
+                    print(format!("&a{}. &f{sentence}", i + 1));
+                }
+            }
+
+            ["add", words @ ..] if !words.is_empty() => {
+                let sentence = normalize_sentence(&Self::resolve_numbered(&words.join(" "))?);
+
+                let mut favorites = FAVORITES.lock();
+                if favorites.add(sentence.clone()) {
+                    favorites.save(FAVORITES_FILE_PATH)?;
+                    print(format!("&eAdded {sentence} to favorites"));
+                } else {
+                    print(format!("&e{sentence} is already a favorite"));
+                }
+            }
+
+            ["remove", words @ ..] if !words.is_empty() => {
+                let sentence = normalize_sentence(&Self::resolve_numbered(&words.join(" "))?);
+
+                let mut favorites = FAVORITES.lock();
+                if favorites.remove(&sentence) {
+                    favorites.save(FAVORITES_FILE_PATH)?;
+                    print(format!("&eRemoved {sentence} from favorites"));
+                } else {
+                    print(format!("&e{sentence} isn't a favorite"));
+                }
+            }
+
+            _ => print(FAV_COMMAND_HELP),
+        }
+
+        Ok(())
+    }
+
+    /// Numbered after the favorites, so `#n` means the same thing everywhere.
+    fn recent_command() {
+        let mut number = FAVORITES.lock().sentences().len();
+        let recent = RECENT.lock();
+
+        for (title, sentences) in [
+            ("Recently played", recent.played()),
+            ("Recently heard", recent.heard()),
+        ] {
+            if sentences.is_empty() {
+                continue;
+            }
+
+            print(format!("&e{title}:"));
+            for sentence in sentences {
+                number += 1;
+                print(format!("&a{number}. &f{sentence}"));
+            }
+        }
+    }
+
+    /// Favorites, then recently played, then recently heard, in the order
+    /// `fav list` and `recent` number them.
+    fn numbered_sentences() -> Vec<String> {
+        let mut sentences = FAVORITES.lock().sentences().to_vec();
+
+        let recent = RECENT.lock();
+        sentences.extend(recent.played().iter().cloned());
+        sentences.extend(recent.heard().iter().cloned());
+
+        sentences
+    }
+
+    /// `#3` is the 3rd of `numbered_sentences`, anything else stays as is.
+    fn resolve_numbered(text: &str) -> Result<String> {
+        let Some(number) = text
+            .strip_prefix('#')
+            .and_then(|number| number.parse::<usize>().ok())
+        else {
+            return Ok(text.to_string());
+        };
+
+        number
+            .checked_sub(1)
+            .and_then(|i| Self::numbered_sentences().into_iter().nth(i))
+            .ok_or_else(|| anyhow!("no entry #{number}, see fav list and recent"))
+    }
+
+    fn bind_command(args: &[&str]) -> Result<()> {
+        let mut soundboard = SOUNDBOARD.lock();
+
+        match args {
+            [] => {
+                print(BIND_COMMAND_HELP);
+                for (combo, sentence) in soundboard.binds() {
+                    print(format!("&e{combo}: &f{sentence}"));
+                }
+            }
+
+            [combo] => {
+                let combo: KeyCombo = combo.parse()?;
+                match soundboard.get(combo.key, combo.ctrl, combo.shift) {
+                    Some(sentence) => print(format!("&e{combo}: &f{sentence}")),
+                    None => print(format!("&e{combo} isn't bound")),
+                }
+            }
+
+            [combo, words @ ..] => {
+                let combo: KeyCombo = combo.parse()?;
+                let sentence = words.join(" ");
+
+                soundboard.bind(combo, sentence.clone())?;
+                print(format!("&eBound {combo} to {sentence}"));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn key_command(args: &[&str]) -> Result<()> {
+        let mut keybinds = *KEYBINDS.lock();
+
+        let [action, combo] = args else {
+            print(KEY_COMMAND_HELP);
+            for action in KeyAction::ALL {
+                print(format!(
+                    "&e{action}: &f{}",
+                    keybinds
+                        .get(action)
+                        .map_or_else(|| "none".to_string(), |combo| combo.to_string())
+                ));
+            }
+            return Ok(());
+        };
+
+        let action: KeyAction = action.parse()?;
+        let combo = if combo.eq_ignore_ascii_case("none") {
+            None
+        } else {
+            Some(combo.parse()?)
+        };
+
+        keybinds.set(action, combo);
+        keybinds.save();
+        *KEYBINDS.lock() = keybinds;
+        print(format!(
+            "&eSet {action} key to {}",
+            combo.map_or_else(|| "none".to_string(), |combo| combo.to_string())
+        ));
+
+        Ok(())
+    }
+
+    fn sources_command() {
+        let statuses = SOURCE_STATUSES.lock();
+        if statuses.is_empty() {
+            print("&eNo sources loaded yet");
+            return;
+        }
+
+        for (i, status) in statuses.iter().enumerate() {
+            print(format!("&a{}. {}", i + 1, status.format()));
+            if let Some(error) = &status.last_error {
+                print(format!("{}   {}", classicube_helpers::color::RED, error));
+            }
+        }
+    }
+
+    fn add_source(config: &mut SourcesConfig, source: Source) -> Result<()> {
+        if config.add(source.clone()) {
+            config.save()?;
+            print(format!("&eAdded {source}"));
+            print(format!("&eRun {RELOAD_COMMAND_HELP} &eto apply"));
+        } else {
+            print(format!("&e{source} is already in the list"));
+        }
+
+        Ok(())
+    }
 }
 
 // hacky fix because c_command_callback can't get instance
@@ -210,6 +887,10 @@ unsafe extern "C" fn c_command_callback(args: *const classicube_sys::cc_string,
                 }
             });
 
+            if let Some(force) = command_module.pending_reload.take() {
+                ChatsoundsModule::reload(command_module.chatsounds.clone(), force);
+            }
+
             let mut event_handler_module = command_module.event_handler_module.borrow_mut();
             event_handler_module.handle_outgoing_events();
         }
@@ -1,4 +1,5 @@
 mod outgoing_events;
+mod press_hook;
 mod types;
 
 use std::{
@@ -16,7 +17,7 @@ use classicube_helpers::{
     tick::TickEventHandler,
 };
 use classicube_sys::{
-    Chat_Add, Chat_AddOf, Event_RaiseInput, Event_RaiseInt, InputDevice, InputEvents,
+    Chat_Add, Chat_AddOf, Chat_Send, Event_RaiseInput, Event_RaiseInt, InputDevice, InputEvents,
     MsgType_MSG_TYPE_NORMAL, OwnedString, Server, WindowInfo,
 };
 use crossbeam_channel::{Receiver, Sender, unbounded};
@@ -24,7 +25,7 @@ pub use outgoing_events::*;
 use parking_lot::Mutex;
 use tracing::debug;
 
-pub use self::types::*;
+pub use self::{press_hook::swallow_next_press, types::*};
 use crate::{
     helpers::{is_global_cs_message, is_global_csent_message, is_global_cspos_message},
     modules::Module,
@@ -101,9 +102,10 @@ impl EventHandlerModule {
     }
 
     pub fn handle_outgoing_events(&mut self) {
-        self.simulating = true;
-
         for event in self.outgoing_event_receiver.try_iter() {
+            // in singleplayer, sending echoes the line straight back through
+            // ChatReceived, which should play like anyone else's
+            self.simulating = !matches!(event, OutgoingEvent::ChatSend(_));
             Self::handle_outgoing_event(event);
         }
 
@@ -120,6 +122,14 @@ impl EventHandlerModule {
                 }
             }
 
+            OutgoingEvent::ChatSend(text) => {
+                let owned_string = OwnedString::new(text);
+
+                unsafe {
+                    Chat_Send(owned_string.as_cc_string(), 0);
+                }
+            }
+
             OutgoingEvent::ChatAddOf(msg, msg_type) => {
                 let owned_string = OwnedString::new(msg);
 
@@ -253,6 +263,7 @@ impl Module for EventHandlerModule {
                 if DEVICE.get().is_none() && !device.is_null() {
                     DEVICE.set(Some(*device));
                 }
+                press_hook::reset();
                 module.handle_incoming_event(&IncomingEvent::InputDown(*key, *repeating));
                 module.handle_outgoing_events();
             },
@@ -268,6 +279,8 @@ impl Module for EventHandlerModule {
             module.handle_incoming_event(&IncomingEvent::InputPress(*key));
             module.handle_outgoing_events();
         });
+        // after our own press handler, so that swallowed presses skip it too
+        press_hook::install();
 
         self.input_up
             .on(move |input::Up2Event { key, repeating, .. }| {
@@ -315,6 +328,7 @@ impl Module for EventHandlerModule {
 
         // classicube-helpers event fields (chat_received, input_*,
         // tick_callback, focus_changed_callback) unregister via Drop when
-        // self is dropped — no manual work needed.
+        // self is dropped, which needs input_press back in InputEvents.Press.
+        press_hook::uninstall();
     }
 }
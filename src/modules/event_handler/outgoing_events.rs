@@ -40,6 +40,10 @@ pub fn chat_add<S: Into<String>>(text: S) {
     }
 }
 
+pub fn chat_send<S: Into<String>>(text: S) {
+    new_outgoing_event(OutgoingEvent::ChatSend(text.into()));
+}
+
 pub fn simulate_key(key: InputButtons) {
     new_outgoing_event(OutgoingEvent::InputDown(key, false));
     new_outgoing_event(OutgoingEvent::InputUp(key, false));
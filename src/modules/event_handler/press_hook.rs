@@ -0,0 +1,107 @@
+use std::{
+    cell::{Cell, RefCell},
+    os::raw::{c_int, c_void},
+    ptr,
+};
+
+use classicube_sys::{Event_Int_Callback, InputEvents};
+
+use super::EVENT_HANDLER_MODULE;
+
+// The handlers that were on InputEvents.Press when the hook was installed
+// (ClassiCube's own, which type into the chat input, and ours), which are only
+// called through the trampoline until it's uninstalled.
+thread_local!(
+    static HOOKED: RefCell<Vec<(Event_Int_Callback, *mut c_void)>> =
+        const { RefCell::new(Vec::new()) };
+);
+
+thread_local!(
+    static SWALLOW_NEXT: Cell<bool> = const { Cell::new(false) };
+);
+
+/// Stops the char of the key that was just pressed from being typed.
+///
+/// Must be called while handling the key's `InputDown`, which always comes
+/// before its `InputPress`.
+pub fn swallow_next_press() {
+    SWALLOW_NEXT.set(true);
+}
+
+/// A press only belongs to the key down right before it.
+pub(super) fn reset() {
+    SWALLOW_NEXT.set(false);
+}
+
+/// Events can't be cancelled, so this moves every `InputEvents.Press` handler
+/// registered so far behind one that can skip them.
+pub(super) fn install() {
+    HOOKED.with_borrow_mut(|hooked| {
+        if !hooked.is_empty() {
+            return;
+        }
+
+        let press = unsafe { &mut *(&raw mut InputEvents.Press) };
+        let count = usize::try_from(press.Count).unwrap_or(0);
+        hooked.extend(
+            press.Handlers[..count]
+                .iter()
+                .copied()
+                .zip(press.Objs[..count].iter().copied()),
+        );
+
+        press.Handlers.fill(None);
+        press.Objs.fill(ptr::null_mut());
+        press.Handlers[0] = Some(trampoline);
+        press.Count = 1;
+    });
+}
+
+/// Puts the hooked handlers back in front of any that were registered since,
+/// so that they can unregister themselves as usual.
+pub(super) fn uninstall() {
+    HOOKED.with_borrow_mut(|hooked| {
+        if hooked.is_empty() {
+            return;
+        }
+
+        let press = unsafe { &mut *(&raw mut InputEvents.Press) };
+        let count = usize::try_from(press.Count).unwrap_or(0);
+        // the trampoline is first, so everything after it came later
+        let added: Vec<_> = (1..count)
+            .map(|i| (press.Handlers[i], press.Objs[i]))
+            .collect();
+
+        press.Handlers.fill(None);
+        press.Objs.fill(ptr::null_mut());
+        let mut count = 0;
+        for (handler, obj) in hooked.drain(..).chain(added) {
+            press.Handlers[count] = handler;
+            press.Objs[count] = obj;
+            count += 1;
+        }
+        press.Count = c_int::try_from(count).unwrap_or(c_int::MAX);
+    });
+
+    reset();
+}
+
+unsafe extern "C" fn trampoline(_obj: *mut c_void, key: c_int) {
+    // our own simulated presses always go through
+    let simulating = EVENT_HANDLER_MODULE
+        .get()
+        .is_some_and(|ptr| unsafe { (*ptr).simulating });
+    if !simulating && SWALLOW_NEXT.replace(false) {
+        return;
+    }
+
+    // copied so that handlers can register others while running
+    let hooked = HOOKED.with_borrow(Clone::clone);
+    for (handler, obj) in hooked {
+        if let Some(handler) = handler {
+            unsafe {
+                handler(obj, key);
+            }
+        }
+    }
+}
@@ -16,6 +16,8 @@ pub enum IncomingEvent {
 #[derive(Debug, Clone)]
 pub enum OutgoingEvent {
     ChatAdd(String),
+    /// sent to the server as if typed in chat
+    ChatSend(String),
     ChatAddOf(String, MsgType),
     InputDown(InputButtons, bool),
     InputUp(InputButtons, bool),
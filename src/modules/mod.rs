@@ -69,12 +69,14 @@ pub fn load() {
         let command_module = Rc::new(RefCell::new(CommandModule::new(
             event_handler_module.clone(),
             chatsounds_module.borrow_mut().chatsounds.clone(),
+            chatsounds_module.borrow_mut().entity_emitters.clone(),
         )));
         modules.push(command_module);
 
         let autocomplete_module = Rc::new(RefCell::new(AutocompleteModule::new(
             option_module,
             chatsounds_module.borrow_mut().chatsounds.clone(),
+            chatsounds_module.borrow_mut().entity_emitters.clone(),
             event_handler_module,
             tab_list,
         )));
@@ -1,23 +1,241 @@
 use std::{
     collections::HashMap,
     ffi::CString,
+    fmt::{self, Display},
     os::raw::c_char,
+    str::FromStr,
     sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
 };
 
+use anyhow::{Context, Error, Result, bail};
 use classicube_sys::{
-    Input_StorageNames, InputButtons, KeyBind_Defaults, Options_Get, Options_Set, OwnedString,
-    STRING_SIZE, bindNames,
+    Input_StorageNames, InputButtons, InputButtons_CCKEY_SPACE, InputButtons_CCKEY_TAB,
+    KeyBind_Defaults, Options, Options_Get, Options_Set, OwnedString, STRING_SIZE,
+    StringsBuffer_UNSAFE_Get, bindNames,
 };
+use parking_lot::Mutex;
 
-use crate::modules::Module;
+use crate::modules::{Module, autocomplete::SearchMode};
 
+pub const ALLOWLIST_ONLY_SETTING_NAME: &str = "chatsounds-allowlist-only";
 pub const AUTOCOMPLETE_SETTING_NAME: &str = "chatsounds-autocomplete";
+pub const AUTOCOMPLETE_MODE_SETTING_NAME: &str = "chatsounds-autocomplete-mode";
+pub const AUTOCOMPLETE_PREVIEW_SETTING_NAME: &str = "chatsounds-autocomplete-preview";
+pub const HISTORY_DEDUPE_SETTING_NAME: &str = "chatsounds-history-dedupe";
+pub const MAX_DURATION_SETTING_NAME: &str = "chatsounds-max-duration";
 pub const MUTE_LOSE_FOCUS_SETTING_NAME: &str = "chatsounds-mute-lose-focus";
+pub const OCCLUSION_SETTING_NAME: &str = "chatsounds-occlusion";
+pub const SH_SELF_ONLY_SETTING_NAME: &str = "chatsounds-sh-self-only";
+pub const SOUNDBOARD_LOCAL_SETTING_NAME: &str = "chatsounds-soundboard-local";
 pub const VOLUME_SETTING_NAME: &str = "chatsounds-volume";
 
+static ALLOWLIST_ONLY: AtomicBool = AtomicBool::new(false);
 static AUTOCOMPLETE: AtomicBool = AtomicBool::new(true);
+static AUTOCOMPLETE_MODE: Mutex<SearchMode> = Mutex::new(SearchMode::Fuzzy);
+static AUTOCOMPLETE_PREVIEW: AtomicBool = AtomicBool::new(false);
+static HISTORY_DEDUPE: AtomicBool = AtomicBool::new(true);
+static MAX_DURATION: Mutex<Option<Duration>> = Mutex::new(None);
 static MUTE_LOSE_FOCUS: AtomicBool = AtomicBool::new(true);
+static OCCLUSION: AtomicBool = AtomicBool::new(false);
+static SH_SELF_ONLY: AtomicBool = AtomicBool::new(false);
+static SOUNDBOARD_LOCAL: AtomicBool = AtomicBool::new(false);
+
+pub static KEYBINDS: Mutex<Keybinds> = Mutex::new(Keybinds::new());
+
+/// A key with Ctrl and/or Shift held, written like `Ctrl+Space` using the
+/// game's key names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub key: InputButtons,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl KeyCombo {
+    const fn new(key: InputButtons) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    const fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    const fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub fn matches(self, key: InputButtons, ctrl: bool, shift: bool) -> bool {
+        self.key == key && self.ctrl == ctrl && self.shift == shift
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (modifiers, key_name) = s.rsplit_once('+').unwrap_or(("", s));
+
+        let key = OptionModule::get_key_from_input_name(key_name)
+            .with_context(|| format!("unknown key {key_name:?}"))?;
+        let mut combo = Self::new(key);
+
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" => combo.ctrl = true,
+                "shift" => combo.shift = true,
+                _ => bail!("expected Ctrl or Shift, got {modifier:?}"),
+            }
+        }
+
+        Ok(combo)
+    }
+}
+
+impl Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+
+        let name = usize::try_from(self.key)
+            .ok()
+            .and_then(|i| Input_StorageNames.get(i).copied())
+            .unwrap_or("Unknown");
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    NextHint,
+    PreviousHint,
+    AcceptHint,
+    Preview,
+    StopAll,
+    Mute,
+}
+
+impl KeyAction {
+    pub const ALL: [Self; 6] = [
+        Self::NextHint,
+        Self::PreviousHint,
+        Self::AcceptHint,
+        Self::Preview,
+        Self::StopAll,
+        Self::Mute,
+    ];
+
+    fn setting_name(self) -> String {
+        format!("chatsounds-key-{self}")
+    }
+}
+
+impl FromStr for KeyAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.to_string() == s)
+            .with_context(|| {
+                let names: Vec<String> = Self::ALL.iter().map(ToString::to_string).collect();
+                format!("expected {}", names.join(", "))
+            })
+    }
+}
+
+impl Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NextHint => write!(f, "next-hint"),
+            Self::PreviousHint => write!(f, "previous-hint"),
+            Self::AcceptHint => write!(f, "accept-hint"),
+            Self::Preview => write!(f, "preview"),
+            Self::StopAll => write!(f, "stop-all"),
+            Self::Mute => write!(f, "mute"),
+        }
+    }
+}
+
+/// Our own keys, on top of the game's `key-*` binds. Each is stored as
+/// `chatsounds-key-<action>`, with `none` for unbound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keybinds {
+    /// indexed by `KeyAction`
+    combos: [Option<KeyCombo>; KeyAction::ALL.len()],
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Keybinds {
+    pub const fn new() -> Self {
+        Self {
+            combos: [
+                Some(KeyCombo::new(InputButtons_CCKEY_TAB)),
+                Some(KeyCombo::new(InputButtons_CCKEY_TAB).with_shift()),
+                None,
+                Some(KeyCombo::new(InputButtons_CCKEY_SPACE).with_ctrl()),
+                None,
+                None,
+            ],
+        }
+    }
+
+    pub fn load() -> Self {
+        let mut keybinds = Self::new();
+        for action in KeyAction::ALL {
+            match OptionModule::get(action.setting_name()).as_deref() {
+                None => {}
+                Some("none") => keybinds.set(action, None),
+                Some(s) => {
+                    if let Ok(combo) = s.parse() {
+                        keybinds.set(action, Some(combo));
+                    }
+                }
+            }
+        }
+        keybinds
+    }
+
+    pub fn save(&self) {
+        for action in KeyAction::ALL {
+            let value = self
+                .get(action)
+                .map_or_else(|| "none".to_string(), |combo| combo.to_string());
+            OptionModule::set(action.setting_name(), value);
+        }
+    }
+
+    pub fn get(&self, action: KeyAction) -> Option<KeyCombo> {
+        self.combos[action as usize]
+    }
+
+    pub fn set(&mut self, action: KeyAction, combo: Option<KeyCombo>) {
+        self.combos[action as usize] = combo;
+    }
+
+    /// The action bound to `key` with exactly these modifiers held.
+    pub fn action(&self, key: InputButtons, ctrl: bool, shift: bool) -> Option<KeyAction> {
+        KeyAction::ALL.into_iter().find(|&action| {
+            self.get(action)
+                .is_some_and(|combo| combo.matches(key, ctrl, shift))
+        })
+    }
+}
 
 pub struct OptionModule {
     pub open_chat_key: Option<InputButtons>,
@@ -65,6 +283,23 @@ impl OptionModule {
         }
     }
 
+    /// Every saved option whose key starts with `prefix`, as the rest of the
+    /// key and the value.
+    pub fn with_prefix(prefix: &str) -> Vec<(String, String)> {
+        let prefix = prefix.to_lowercase();
+
+        let count = unsafe { Options.count };
+        (0..count)
+            .filter_map(|i| {
+                // entries are stored as "key=value"
+                let entry = unsafe { StringsBuffer_UNSAFE_Get(&raw mut Options, i) }.to_string();
+                let (key, value) = entry.split_once('=')?;
+                let rest = key.to_lowercase().strip_prefix(&prefix)?.to_string();
+                Some((rest, value.to_string()))
+            })
+            .collect()
+    }
+
     pub fn set<S: Into<Vec<u8>>>(key: S, value: String) {
         let c_key = CString::new(key).unwrap();
 
@@ -75,6 +310,15 @@ impl OptionModule {
         }
     }
 
+    pub fn allowlist_only() -> bool {
+        ALLOWLIST_ONLY.load(Ordering::Relaxed)
+    }
+
+    pub fn set_allowlist_only(value: bool) {
+        ALLOWLIST_ONLY.store(value, Ordering::Relaxed);
+        Self::set(ALLOWLIST_ONLY_SETTING_NAME, format!("{value}"));
+    }
+
     pub fn autocomplete() -> bool {
         AUTOCOMPLETE.load(Ordering::Relaxed)
     }
@@ -84,6 +328,49 @@ impl OptionModule {
         Self::set(AUTOCOMPLETE_SETTING_NAME, format!("{value}"));
     }
 
+    pub fn autocomplete_mode() -> SearchMode {
+        *AUTOCOMPLETE_MODE.lock()
+    }
+
+    pub fn set_autocomplete_mode(value: SearchMode) {
+        *AUTOCOMPLETE_MODE.lock() = value;
+        Self::set(AUTOCOMPLETE_MODE_SETTING_NAME, format!("{value}"));
+    }
+
+    /// whether the hint selected with Tab is played locally
+    pub fn autocomplete_preview() -> bool {
+        AUTOCOMPLETE_PREVIEW.load(Ordering::Relaxed)
+    }
+
+    pub fn set_autocomplete_preview(value: bool) {
+        AUTOCOMPLETE_PREVIEW.store(value, Ordering::Relaxed);
+        Self::set(AUTOCOMPLETE_PREVIEW_SETTING_NAME, format!("{value}"));
+    }
+
+    /// whether sending a line already in the chat history moves it to the end
+    /// instead of repeating it
+    pub fn history_dedupe() -> bool {
+        HISTORY_DEDUPE.load(Ordering::Relaxed)
+    }
+
+    pub fn set_history_dedupe(value: bool) {
+        HISTORY_DEDUPE.store(value, Ordering::Relaxed);
+        Self::set(HISTORY_DEDUPE_SETTING_NAME, format!("{value}"));
+    }
+
+    /// None means sounds play to completion
+    pub fn max_duration() -> Option<Duration> {
+        *MAX_DURATION.lock()
+    }
+
+    /// 0 disables the limit
+    pub fn set_max_duration(secs: f32) -> Result<()> {
+        let max_duration = Duration::try_from_secs_f32(secs)?;
+        *MAX_DURATION.lock() = Some(max_duration).filter(|max_duration| !max_duration.is_zero());
+        Self::set(MAX_DURATION_SETTING_NAME, format!("{secs}"));
+        Ok(())
+    }
+
     pub fn mute_lose_focus() -> bool {
         MUTE_LOSE_FOCUS.load(Ordering::Relaxed)
     }
@@ -93,6 +380,36 @@ impl OptionModule {
         Self::set(MUTE_LOSE_FOCUS_SETTING_NAME, format!("{value}"));
     }
 
+    /// whether sounds behind blocks are quieter
+    pub fn occlusion() -> bool {
+        OCCLUSION.load(Ordering::Relaxed)
+    }
+
+    pub fn set_occlusion(value: bool) {
+        OCCLUSION.store(value, Ordering::Relaxed);
+        Self::set(OCCLUSION_SETTING_NAME, format!("{value}"));
+    }
+
+    /// whether `sh` in chat only stops the speaker's own sounds
+    pub fn sh_self_only() -> bool {
+        SH_SELF_ONLY.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sh_self_only(value: bool) {
+        SH_SELF_ONLY.store(value, Ordering::Relaxed);
+        Self::set(SH_SELF_ONLY_SETTING_NAME, format!("{value}"));
+    }
+
+    /// whether soundboard keys only play for us instead of sending to chat
+    pub fn soundboard_local() -> bool {
+        SOUNDBOARD_LOCAL.load(Ordering::Relaxed)
+    }
+
+    pub fn set_soundboard_local(value: bool) {
+        SOUNDBOARD_LOCAL.store(value, Ordering::Relaxed);
+        Self::set(SOUNDBOARD_LOCAL_SETTING_NAME, format!("{value}"));
+    }
+
     fn get_all_keybinds() -> HashMap<&'static str, InputButtons> {
         let mut map = HashMap::with_capacity(bindNames.len());
 
@@ -116,19 +433,63 @@ impl Module for OptionModule {
 
         self.open_chat_key = keybinds.get("Chat").copied();
         self.send_chat_key = keybinds.get("SendChat").copied();
+        *KEYBINDS.lock() = Keybinds::load();
 
+        ALLOWLIST_ONLY.store(
+            Self::get(ALLOWLIST_ONLY_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            Ordering::Relaxed,
+        );
         AUTOCOMPLETE.store(
             Self::get(AUTOCOMPLETE_SETTING_NAME)
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(true),
             Ordering::Relaxed,
         );
+        *AUTOCOMPLETE_MODE.lock() = Self::get(AUTOCOMPLETE_MODE_SETTING_NAME)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(SearchMode::Fuzzy);
+        AUTOCOMPLETE_PREVIEW.store(
+            Self::get(AUTOCOMPLETE_PREVIEW_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            Ordering::Relaxed,
+        );
+        HISTORY_DEDUPE.store(
+            Self::get(HISTORY_DEDUPE_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+            Ordering::Relaxed,
+        );
         MUTE_LOSE_FOCUS.store(
             Self::get(MUTE_LOSE_FOCUS_SETTING_NAME)
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(true),
             Ordering::Relaxed,
         );
+        OCCLUSION.store(
+            Self::get(OCCLUSION_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            Ordering::Relaxed,
+        );
+        SH_SELF_ONLY.store(
+            Self::get(SH_SELF_ONLY_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            Ordering::Relaxed,
+        );
+        SOUNDBOARD_LOCAL.store(
+            Self::get(SOUNDBOARD_LOCAL_SETTING_NAME)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            Ordering::Relaxed,
+        );
+        *MAX_DURATION.lock() = Self::get(MAX_DURATION_SETTING_NAME)
+            .and_then(|s| s.parse().ok())
+            .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+            .filter(|max_duration| !max_duration.is_zero());
     }
 
     fn unload(&mut self) {}
@@ -1,6 +1,9 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use classicube_sys::MsgType_MSG_TYPE_CLIENTSTATUS_2;
+use classicube_sys::{
+    MsgType, MsgType_MSG_TYPE_BOTTOMRIGHT_1, MsgType_MSG_TYPE_BOTTOMRIGHT_2,
+    MsgType_MSG_TYPE_BOTTOMRIGHT_3, MsgType_MSG_TYPE_CLIENTSTATUS_2,
+};
 use parking_lot::Mutex;
 use tracing::info;
 
@@ -8,6 +11,16 @@ use crate::modules::event_handler::{IncomingEvent, IncomingEventListener, chat_a
 
 pub static PRINTER: Mutex<Printer> = Mutex::new(Printer::new());
 
+/// How long a `status` message stays up.
+const STATUS_DURATION: Duration = Duration::from_secs(2);
+
+/// Message slots for `status_list`, top to bottom.
+const STATUS_LIST_SLOTS: [MsgType; 3] = [
+    MsgType_MSG_TYPE_BOTTOMRIGHT_3,
+    MsgType_MSG_TYPE_BOTTOMRIGHT_2,
+    MsgType_MSG_TYPE_BOTTOMRIGHT_1,
+];
+
 pub struct Printer {
     status_decay: Option<Instant>,
 }
@@ -21,6 +34,11 @@ impl Printer {
         chat_add(s);
     }
 
+    pub fn status<T: Into<String>>(&mut self, s: T) {
+        chat_add_of(s, MsgType_MSG_TYPE_CLIENTSTATUS_2);
+        self.status_decay = Some(Instant::now() + STATUS_DURATION);
+    }
+
     pub fn status_forever<T: Into<String>>(&mut self, s: T) {
         chat_add_of(s, MsgType_MSG_TYPE_CLIENTSTATUS_2);
         self.status_decay = None;
@@ -52,7 +70,20 @@ pub fn print<T: Into<String>>(s: T) {
     Printer::print(s);
 }
 
+pub fn status<T: Into<String>>(s: T) {
+    let s = s.into();
+    info!("{}", s);
+    PRINTER.lock().status(s);
+}
+
 pub fn status_forever<T: Into<String>>(s: T) {
     let s = s.into();
     PRINTER.lock().status_forever(s);
 }
+
+/// Shows up to 3 lines in the bottom right, clearing the rest.
+pub fn status_list(lines: &[String]) {
+    for (i, slot) in STATUS_LIST_SLOTS.into_iter().enumerate() {
+        chat_add_of(lines.get(i).map_or("", String::as_str), slot);
+    }
+}